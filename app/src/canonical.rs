@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use leptos::prelude::*;
+
+use crate::talent_configuration::{TalentConfiguration, TalentConfigurationError};
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::{use_trait_tree_data, TraitTree, TraitTreeNodeType};
+
+/*
+ * Strings for the same build can differ in padding chars past the last node, a zero or the real
+ * tree hash, rank bits on maxed nodes, granted nodes left out or written as purchased, or points
+ * left in the hero tree that is not selected.
+ * The canonical string is the decoded build re-encoded with all of those normalized, carrying the
+ * tree hash from the data or zeros when the data has none. A hash that does not match the data
+ * fails to decode and the line is reported as invalid.
+ */
+
+impl TalentConfiguration {
+    // hero points only in the selected hero tree and no rank above its maximum
+    pub fn canonical(&self, config: &TalentEncoding) -> Self {
+        let mut canonical = self.clone();
+        for talent in canonical.all_talents.iter_mut() {
+            let node = &talent.trait_tree_node;
+            if node.node_type == TraitTreeNodeType::SubTree {
+                continue;
+            }
+            if node
+                .trait_sub_tree_id
+                .is_some_and(|id| !self.subtrees.contains(&id))
+            {
+                talent.rank = 0;
+            }
+            talent.rank = talent.rank.min(talent.max_ranks());
+        }
+        canonical.refresh_selection();
+        canonical.string = canonical.encode(config);
+        canonical
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Deduplicated {
+    // canonical strings in order of first appearance
+    pub unique: Vec<String>,
+    pub duplicates: usize,
+    // input line and why it does not decode
+    pub invalid: Vec<(String, String)>,
+}
+
+// blank lines are skipped, everything else is decoded once
+pub fn dedupe_strings<'a>(
+    strings: impl IntoIterator<Item = &'a str>,
+    config: &TalentEncoding,
+    trait_trees: &[TraitTree],
+) -> Deduplicated {
+    let mut deduplicated = Deduplicated::default();
+    let mut seen = HashSet::new();
+    // lists are usually one spec, decoding against its tree alone skips cloning every other one
+    let mut last_tree: Option<TraitTree> = None;
+
+    for s in strings.into_iter().map(str::trim).filter(|s| !s.is_empty()) {
+        let decoded = match last_tree.take() {
+            Some(tree) => match TalentConfiguration::new_from_str(s, config.clone(), vec![tree]) {
+                Err(TalentConfigurationError::SpecNotFound) => {
+                    TalentConfiguration::new_from_str(s, config.clone(), trait_trees.to_vec())
+                }
+                decoded => decoded,
+            },
+            None => TalentConfiguration::new_from_str(s, config.clone(), trait_trees.to_vec()),
+        };
+        match decoded {
+            Ok(configuration) => {
+                let canonical = configuration.canonical(config);
+                match seen.insert(canonical.string.clone()) {
+                    true => deduplicated.unique.push(canonical.string),
+                    false => deduplicated.duplicates += 1,
+                }
+                last_tree = Some(configuration.trait_tree);
+            }
+            Err(err) => deduplicated.invalid.push((s.to_string(), err.to_string())),
+        }
+    }
+    deduplicated
+}
+
+#[component]
+pub fn DedupeView(talent_encoding: ReadSignal<TalentEncoding>) -> impl IntoView {
+    let trait_tree_data = use_trait_tree_data();
+    let (input, set_input) = signal(String::new());
+    let (result, set_result) = signal(None::<Result<Deduplicated, String>>);
+
+    let dedupe = move |_| {
+        let Some(Ok(trait_trees)) = trait_tree_data.get() else {
+            set_result.set(Some(Err("Talent data is not loaded yet".to_string())));
+            return;
+        };
+        let deduplicated =
+            input.with(|input| dedupe_strings(input.lines(), &talent_encoding.get(), &trait_trees));
+        set_result.set(Some(Ok(deduplicated)));
+    };
+
+    view! {
+        <div class="dedupe">
+            <textarea
+                placeholder="Talent strings, one per line"
+                on:input:target=move |tag| set_input.set(tag.target().value())
+                prop:value=input
+            />
+            <button on:click=dedupe>"Deduplicate"</button>
+            {move || {
+                result
+                    .get()
+                    .map(|result| match result {
+                        Err(err) => view! { <div>{err}</div> }.into_any(),
+                        Ok(deduplicated) => {
+                            view! {
+                                <div>
+                                    {format!(
+                                        "{} unique, {} duplicates, {} invalid",
+                                        deduplicated.unique.len(),
+                                        deduplicated.duplicates,
+                                        deduplicated.invalid.len(),
+                                    )}
+                                </div>
+                                <textarea readonly=true prop:value=deduplicated.unique.join("\n") />
+                                <ul>
+                                    {deduplicated
+                                        .invalid
+                                        .into_iter()
+                                        .map(|(s, err)| view! { <li>{format!("{s}: {err}")}</li> })
+                                        .collect::<Vec<_>>()}
+                                </ul>
+                            }
+                                .into_any()
+                        }
+                    })
+            }}
+        </div>
+    }
+}
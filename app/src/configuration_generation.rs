@@ -1,25 +1,617 @@
+use std::collections::HashSet;
+
 use leptos::prelude::*;
 
-use crate::talent_configuration::{DrawTalentConfigView, TalentConfiguration};
-use crate::trait_tree::fetch_trait_trees;
+use crate::constraints::{ConstraintSet, NodeTarget};
+use crate::layout::LayoutSection;
+use crate::solver::{SolvedBuild, SolverOutcome, SolverSettings, Weights};
+use crate::talent_configuration::{DrawTalentConfigView, TalentConfiguration, TalentEntry};
+use crate::trait_tree::{use_trait_tree_data, TraitTreeNodeType};
+use crate::weight_import::WeightImportView;
 use crate::TalentEncoding;
 
+const BUILDS_PER_PAGE: usize = 12;
+const DEFAULT_BUILDS_PER_HERO_TREE: usize = 12;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildSort {
+    #[default]
+    Generated,
+    PointsSpent,
+    Differences,
+    String,
+}
+
+impl BuildSort {
+    const ALL: [BuildSort; 4] = [
+        BuildSort::Generated,
+        BuildSort::PointsSpent,
+        BuildSort::Differences,
+        BuildSort::String,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            BuildSort::Generated => "generated",
+            BuildSort::PointsSpent => "points",
+            BuildSort::Differences => "differences",
+            BuildSort::String => "string",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            BuildSort::Generated => "Generation order",
+            BuildSort::PointsSpent => "Points spent",
+            BuildSort::Differences => "Most differences",
+            BuildSort::String => "Talent string",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildSummary {
+    // position in generation order
+    pub index: usize,
+    pub configuration: TalentConfiguration,
+    pub class_points: usize,
+    pub spec_points: usize,
+    pub hero_points: usize,
+    // names of the talents this build takes that not every build takes
+    pub differences: Vec<String>,
+}
+
+impl BuildSummary {
+    pub fn total_points(&self) -> usize {
+        self.class_points + self.spec_points + self.hero_points
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty()
+            || self.configuration.string.to_lowercase().contains(&filter)
+            || self
+                .differences
+                .iter()
+                .any(|name| name.to_lowercase().contains(&filter))
+    }
+}
+
+// node, chosen entry and rank of every allocated talent
+fn allocations(configuration: &TalentConfiguration) -> HashSet<(usize, Option<usize>, usize)> {
+    configuration
+        .selected_talents
+        .iter()
+        .map(|te| (te.trait_tree_node.id, te.trait_tree_entry.id, te.rank))
+        .collect()
+}
+
+pub fn talent_name(talent: &TalentEntry) -> String {
+    talent
+        .trait_tree_entry
+        .name
+        .clone()
+        .unwrap_or_else(|| talent.trait_tree_node.name.clone())
+}
+
+pub fn summarize_builds(builds: &[TalentConfiguration]) -> Vec<BuildSummary> {
+    let common = builds
+        .iter()
+        .map(allocations)
+        .reduce(|common, allocations| &common & &allocations)
+        .unwrap_or_default();
+
+    builds
+        .iter()
+        .enumerate()
+        .map(|(index, configuration)| {
+            let differences = configuration
+                .selected_talents
+                .iter()
+                .filter(|te| {
+                    !common.contains(&(te.trait_tree_node.id, te.trait_tree_entry.id, te.rank))
+                })
+                .map(talent_name)
+                .collect();
+            BuildSummary {
+                index,
+                class_points: configuration.points_spent(LayoutSection::Class),
+                spec_points: configuration.points_spent(LayoutSection::Spec),
+                hero_points: configuration
+                    .subtrees
+                    .iter()
+                    .map(|id| configuration.points_spent(LayoutSection::Hero(*id)))
+                    .sum(),
+                configuration: configuration.clone(),
+                differences,
+            }
+        })
+        .collect()
+}
+
+pub fn sort_builds(builds: &mut [BuildSummary], sort: BuildSort) {
+    match sort {
+        BuildSort::Generated => builds.sort_by_key(|build| build.index),
+        BuildSort::PointsSpent => {
+            builds.sort_by_key(|build| (std::cmp::Reverse(build.total_points()), build.index))
+        }
+        BuildSort::Differences => {
+            builds.sort_by_key(|build| (std::cmp::Reverse(build.differences.len()), build.index))
+        }
+        BuildSort::String => builds.sort_by(|a, b| {
+            a.configuration
+                .string
+                .cmp(&b.configuration.string)
+                .then(a.index.cmp(&b.index))
+        }),
+    }
+}
+
+#[component]
+fn BuildThumbnail(summary: BuildSummary, set_talent_str: WriteSignal<String>) -> impl IntoView {
+    let string = summary.configuration.string.clone();
+    let configuration = summary.configuration.clone();
+    let talent_configuration = Memo::new(move |_| Ok(configuration.clone()));
+    let differences = match summary.differences.is_empty() {
+        true => "Same as every other build".to_string(),
+        false => summary.differences.join(", "),
+    };
+
+    view! {
+        <div class="build-grid-item" on:click=move |_| set_talent_str.set(string.clone())>
+            <DrawTalentConfigView talent_configuration compact=true />
+            <div class="build-grid-string">{summary.configuration.string.clone()}</div>
+            <div>
+                {format!(
+                    "Class {} / Spec {} / Hero {}",
+                    summary.class_points,
+                    summary.spec_points,
+                    summary.hero_points,
+                )}
+            </div>
+            <div>{differences}</div>
+        </div>
+    }
+}
+
+#[component]
+pub fn BuildGrid(
+    #[prop(into)] builds: Signal<Vec<TalentConfiguration>>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let (filter, set_filter) = signal(String::new());
+    let (sort, set_sort) = signal(BuildSort::default());
+    let (page, set_page) = signal(0usize);
+
+    let summaries = Memo::new(move |_| builds.with(|builds| summarize_builds(builds)));
+    let filtered = Memo::new(move |_| {
+        let mut filtered = summaries.with(|summaries| {
+            summaries
+                .iter()
+                .filter(|build| build.matches(&filter.get()))
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        sort_builds(&mut filtered, sort.get());
+        filtered
+    });
+    let page_count = move || filtered.with(|builds| builds.len().div_ceil(BUILDS_PER_PAGE).max(1));
+    // a narrower filter can leave the current page past the end
+    let current_page = move || page.get().min(page_count() - 1);
+    let visible = move || {
+        filtered.with(|builds| {
+            builds
+                .iter()
+                .skip(current_page() * BUILDS_PER_PAGE)
+                .take(BUILDS_PER_PAGE)
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    };
+
+    view! {
+        <div class="build-grid">
+            <div>
+                <input
+                    type="text"
+                    placeholder="Filter by string or talent"
+                    on:input:target=move |tag| {
+                        set_filter.set(tag.target().value());
+                        set_page.set(0);
+                    }
+                    prop:value=filter
+                />
+                <select on:input:target=move |tag| {
+                    if let Some(selected) = BuildSort::ALL
+                        .into_iter()
+                        .find(|s| s.name() == tag.target().value())
+                    {
+                        set_sort.set(selected);
+                    }
+                }>
+                    {BuildSort::ALL
+                        .into_iter()
+                        .map(|s| view! { <option value=s.name()>{s.label()}</option> })
+                        .collect::<Vec<_>>()}
+                </select>
+                <span>{move || format!("{} builds", filtered.with(Vec::len))}</span>
+            </div>
+            <div class="build-grid-items">
+                <For
+                    each=visible
+                    key=|build| (build.index, build.configuration.string.clone())
+                    let(summary)
+                >
+                    <BuildThumbnail summary set_talent_str />
+                </For>
+            </div>
+            <div>
+                <button
+                    disabled=move || current_page() == 0
+                    on:click=move |_| set_page.set(current_page().saturating_sub(1))
+                >
+                    "Previous"
+                </button>
+                <span>{move || format!("Page {} of {}", current_page() + 1, page_count())}</span>
+                <button
+                    disabled=move || current_page() + 1 >= page_count()
+                    on:click=move |_| set_page.set(current_page() + 1)
+                >
+                    "Next"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightRow {
+    // class, spec or hero tree name
+    pub section: String,
+    pub name: String,
+    pub target: NodeTarget,
+    pub max_ranks: usize,
+}
+
+// every weighable talent, choice nodes once per entry
+pub fn weight_rows(configuration: &TalentConfiguration) -> Vec<WeightRow> {
+    let hero_trees = configuration.hero_trees();
+    let tree = &configuration.trait_tree;
+    [
+        (&tree.class_nodes, "Class"),
+        (&tree.spec_nodes, "Spec"),
+        (&tree.hero_nodes, "Hero"),
+    ]
+    .into_iter()
+    .flat_map(|(nodes, section)| nodes.iter().map(move |node| (node, section)))
+    // granted nodes are in every build
+    .filter(|(node, _)| !node.is_free())
+    .flat_map(|(node, section)| {
+        let section = node
+            .trait_sub_tree_id
+            .and_then(|id| hero_trees.iter().find(|(hero_tree, _)| *hero_tree == id))
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| section.to_string());
+        let max_ranks = node.max_ranks.unwrap_or(1);
+        match node.node_type {
+            TraitTreeNodeType::Choice => node
+                .entries
+                .iter()
+                .map(|entry| WeightRow {
+                    section: section.clone(),
+                    name: entry.name.clone().unwrap_or_else(|| node.name.clone()),
+                    target: NodeTarget {
+                        node_id: node.id,
+                        entry_id: entry.id,
+                    },
+                    max_ranks: entry.max_ranks.unwrap_or(max_ranks),
+                })
+                .collect::<Vec<_>>(),
+            _ => vec![WeightRow {
+                section,
+                name: node.name.clone(),
+                target: NodeTarget {
+                    node_id: node.id,
+                    entry_id: None,
+                },
+                max_ranks,
+            }],
+        }
+    })
+    .collect()
+}
+
+// comma separated value per rank, None if any of them is not a number
+fn parse_weight(text: &str) -> Option<Vec<f64>> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<f64>().ok())
+        .collect()
+}
+
+#[component]
+fn WeightsEditor(rows: Vec<WeightRow>, weights: RwSignal<Weights>) -> impl IntoView {
+    view! {
+        <details>
+            <summary>"Talent weights"</summary>
+            <table>
+                <tr>
+                    <th>"Section"</th>
+                    <th>"Talent"</th>
+                    <th>"Value per rank"</th>
+                </tr>
+                {rows
+                    .into_iter()
+                    .map(|row| {
+                        let target = row.target;
+                        let current = move || {
+                            weights
+                                .with(|weights| {
+                                    weights
+                                        .get(&target)
+                                        .map(|values| {
+                                            values
+                                                .iter()
+                                                .map(f64::to_string)
+                                                .collect::<Vec<_>>()
+                                                .join(", ")
+                                        })
+                                        .unwrap_or_default()
+                                })
+                        };
+                        let placeholder = match row.max_ranks {
+                            1 => "0".to_string(),
+                            ranks => vec!["0"; ranks].join(", "),
+                        };
+                        view! {
+                            <tr>
+                                <td>{row.section}</td>
+                                <td>{row.name}</td>
+                                <td>
+                                    <input
+                                        type="text"
+                                        placeholder=placeholder
+                                        prop:value=current
+                                        on:change:target=move |tag| {
+                                            if let Some(values) = parse_weight(&tag.target().value()) {
+                                                weights.update(|weights| weights.set(target, values));
+                                            }
+                                        }
+                                    />
+                                </td>
+                            </tr>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </table>
+        </details>
+    }
+}
+
+#[component]
+fn BuildResults(
+    #[prop(into)] outcome: Signal<SolverOutcome>,
+    #[prop(into)] hero_trees: Signal<Vec<(usize, String)>>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let groups = move || {
+        // builds arrive grouped by hero tree
+        let mut groups: Vec<Vec<SolvedBuild>> = Vec::new();
+        outcome.with(|outcome| {
+            for build in &outcome.builds {
+                match groups.last_mut() {
+                    Some(group) if group[0].hero_tree == build.hero_tree => {
+                        group.push(build.clone())
+                    }
+                    _ => groups.push(vec![build.clone()]),
+                }
+            }
+        });
+        groups
+    };
+    let hero_tree_name = move |hero_tree: Option<usize>| {
+        hero_trees.with(|hero_trees| {
+            hero_tree
+                .and_then(|id| hero_trees.iter().find(|(hero_tree, _)| *hero_tree == id))
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| "No hero tree".to_string())
+        })
+    };
+
+    view! {
+        <div class="build-results">
+            {move || {
+                groups()
+                    .into_iter()
+                    .map(|builds| {
+                        view! {
+                            <h4>{hero_tree_name(builds[0].hero_tree)}</h4>
+                            <ol>
+                                {builds
+                                    .into_iter()
+                                    .map(|build| {
+                                        let string = build.configuration.string;
+                                        let load = string.clone();
+                                        view! {
+                                            <li>
+                                                <button on:click=move |_| {
+                                                    set_talent_str.set(load.clone())
+                                                }>"Load"</button>
+                                                {format!(" {:.2} ", build.score)}
+                                                <span class="build-grid-string">{string}</span>
+                                            </li>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </ol>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+        </div>
+    }
+}
+
 #[component]
-pub fn TalentConfigurationGeneration(talent_encoding: ReadSignal<TalentEncoding>) -> impl IntoView {
-    let trait_tree_data = LocalResource::new(move || fetch_trait_trees());
+pub fn TalentConfigurationGeneration(
+    talent_encoding: ReadSignal<TalentEncoding>,
+    talent_str: ReadSignal<String>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let trait_tree_data = use_trait_tree_data();
+    // builds are generated for the spec of the current talent string
+    let spec = Memo::new(move |_| {
+        talent_str.with(|s| TalentConfiguration::spec_from_str(s, &talent_encoding.get()))
+    });
+    let (constraint_text, set_constraint_text) = signal(String::new());
+    let constraint_set =
+        Memo::new(move |_| constraint_text.with(|text| text.parse::<ConstraintSet>()));
+    let weights = RwSignal::new(Weights::default());
+    let (builds_per_hero_tree, set_builds_per_hero_tree) = signal(DEFAULT_BUILDS_PER_HERO_TREE);
 
     let fallback = || view! { <div>"Loading..."</div> };
 
     view! {
+        <div>
+            <textarea
+                placeholder="Constraints, e.g. require \"Node A\" at 2 ranks"
+                on:input:target=move |tag| set_constraint_text.set(tag.target().value())
+                prop:value=constraint_text
+            />
+            <button on:click=move |_| {
+                if let Ok(set) = constraint_set.get() {
+                    set_constraint_text.set(set.to_string());
+                }
+            }>"As text"</button>
+            <button on:click=move |_| {
+                if let Ok(set) = constraint_set.get() {
+                    set_constraint_text.set(set.to_json());
+                }
+            }>"As JSON"</button>
+        </div>
+        <div>
+            <label>
+                "Builds per hero tree "
+                <input
+                    type="number"
+                    min="1"
+                    prop:value=move || builds_per_hero_tree.get().to_string()
+                    on:change:target=move |tag| {
+                        if let Ok(count) = tag.target().value().parse::<usize>() {
+                            set_builds_per_hero_tree.set(count.max(1));
+                        }
+                    }
+                />
+            </label>
+        </div>
         <Transition fallback>
             {move || Suspend::new(async move {
                 trait_tree_data
                     .await
                     .map(|trait_trees| {
-                        let talent_configuration = Memo::new(move |_| {
-                            TalentConfiguration::new(talent_encoding.get(), trait_trees.clone())
+                        let base = Memo::new(move |_| {
+                            TalentConfiguration::new(
+                                spec.get()?,
+                                talent_encoding.get(),
+                                trait_trees.clone(),
+                            )
+                        });
+                        // constraints name nodes of the spec being generated for
+                        let constraints = Memo::new(move |_| {
+                            let set = constraint_set.get().map_err(|err| err.to_string())?;
+                            match base.get() {
+                                Ok(configuration) => {
+                                    set.resolve(&configuration).map_err(|err| err.to_string())
+                                }
+                                Err(err) => Err(err.to_string()),
+                            }
+                        });
+                        // the search can take a while, it only runs when asked to
+                        let outcome = RwSignal::new(None::<SolverOutcome>);
+                        let generate = move |_| {
+                            let (Ok(configuration), Ok(constraints)) = (
+                                base.get_untracked(),
+                                constraints.get_untracked(),
+                            ) else {
+                                return;
+                            };
+                            let settings = SolverSettings {
+                                max_builds: builds_per_hero_tree.get_untracked(),
+                                per_hero_tree: true,
+                                ..Default::default()
+                            };
+                            let solved = weights
+                                .with_untracked(|weights| {
+                                    configuration.solve(
+                                        &constraints,
+                                        weights,
+                                        &settings,
+                                        &talent_encoding.get_untracked(),
+                                    )
+                                });
+                            outcome.set(Some(solved));
+                        };
+                        let builds = Signal::derive(move || {
+                            outcome
+                                .with(|outcome| {
+                                    outcome
+                                        .iter()
+                                        .flat_map(|outcome| &outcome.builds)
+                                        .map(|build| build.configuration.clone())
+                                        .collect::<Vec<_>>()
+                                })
+                        });
+                        // only change with the spec, not with every allocation
+                        let trait_tree = Memo::new(move |_| {
+                            base.with(|base| {
+                                base.as_ref().ok().map(|configuration| configuration.trait_tree.clone())
+                            })
+                        });
+                        let rows = Memo::new(move |_| {
+                            base.with(|base| base.as_ref().map(weight_rows).unwrap_or_default())
+                        });
+                        let hero_trees = Memo::new(move |_| {
+                            base.with(|base| {
+                                base.as_ref()
+                                    .map(|configuration| configuration.hero_trees())
+                                    .unwrap_or_default()
+                            })
                         });
-                        view! { <DrawTalentConfigView talent_configuration /> }
+                        view! {
+                            {move || view! { <WeightsEditor rows=rows.get() weights /> }}
+                            {move || {
+                                trait_tree
+                                    .get()
+                                    .map(|trait_tree| view! { <WeightImportView trait_tree weights /> })
+                            }}
+                            {move || constraints.get().err().map(|err| view! { <div>{err}</div> })}
+                            <button
+                                on:click=generate
+                                disabled=move || base.with(Result::is_err) || constraints.with(Result::is_err)
+                            >
+                                "Generate"
+                            </button>
+                            {move || {
+                                outcome
+                                    .with(|outcome| outcome.as_ref().is_some_and(|outcome| !outcome.complete))
+                                    .then(|| {
+                                        view! {
+                                            <div>
+                                                "The search stopped early, more builds may exist."
+                                            </div>
+                                        }
+                                    })
+                            }}
+                            <Show when=move || weights.with(|weights| !weights.is_empty())>
+                                <BuildResults
+                                    outcome=Signal::derive(move || outcome.get().unwrap_or_default())
+                                    hero_trees
+                                    set_talent_str
+                                />
+                            </Show>
+                            <BuildGrid builds set_talent_str />
+                        }
                     })
             })}
         </Transition>
@@ -0,0 +1,717 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::layout::LayoutSection;
+use crate::talent_configuration::TalentConfiguration;
+use crate::trait_tree::{TraitTreeNode, TraitTreeNodeType};
+
+/*
+ * Constraint language, one constraint per line or separated by `;`, `#` starts a comment. Both
+ * are ignored inside quotes.
+ * Nodes are referenced by node id, or by node or entry name (quoted if it contains keywords):
+
+require 12345
+require "Node A" at 2 ranks
+avoid D
+exactly one of {B, "C"}
+hero tree X
+spend all spec points
+
+ * The same set as JSON is an array of tagged objects:
+
+[
+  { "type": "require", "node": "Node A", "ranks": 2 },
+  { "type": "avoid", "node": 12345 },
+  { "type": "exactly_one", "nodes": ["B", "C"] },
+  { "type": "hero_tree", "tree": "X" },
+  { "type": "spend_all", "section": "spec" }
+]
+
+ */
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConstraintError {
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("Invalid constraint JSON: {0}")]
+    Json(String),
+    #[error("No talent matches {0}")]
+    UnknownNode(NodeRef),
+    #[error("{0} matches more than one talent, use its node id instead")]
+    AmbiguousNode(NodeRef),
+    #[error("No hero tree matches {0}")]
+    UnknownHeroTree(NodeRef),
+    #[error("{node} has {max_ranks} ranks, cannot require {ranks}")]
+    InvalidRanks {
+        node: NodeRef,
+        ranks: usize,
+        max_ranks: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NodeRef {
+    Id(usize),
+    Name(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointSection {
+    Class,
+    Spec,
+    Hero,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Constraint {
+    // without ranks the node has to be maxed
+    Require {
+        node: NodeRef,
+        #[serde(default)]
+        ranks: Option<usize>,
+    },
+    Avoid {
+        node: NodeRef,
+    },
+    ExactlyOne {
+        nodes: Vec<NodeRef>,
+    },
+    HeroTree {
+        tree: NodeRef,
+    },
+    SpendAll {
+        section: PointSection,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ConstraintSet {
+    pub constraints: Vec<Constraint>,
+}
+
+// a node, narrowed to one entry when a choice option was named
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeTarget {
+    pub node_id: usize,
+    pub entry_id: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolvedConstraint {
+    Require { target: NodeTarget, ranks: usize },
+    Avoid { target: NodeTarget },
+    ExactlyOne { targets: Vec<NodeTarget> },
+    HeroTree { trait_sub_tree_id: usize },
+    SpendAll { section: PointSection },
+}
+
+impl PointSection {
+    pub fn covers(&self, section: LayoutSection) -> bool {
+        matches!(
+            (self, section),
+            (PointSection::Class, LayoutSection::Class)
+                | (PointSection::Spec, LayoutSection::Spec)
+                | (PointSection::Hero, LayoutSection::Hero(_))
+        )
+    }
+}
+
+impl fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeRef::Id(id) => write!(f, "{id}"),
+            NodeRef::Name(name) => write!(f, "\"{name}\""),
+        }
+    }
+}
+
+impl fmt::Display for PointSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointSection::Class => write!(f, "class"),
+            PointSection::Spec => write!(f, "spec"),
+            PointSection::Hero => write!(f, "hero"),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Require { node, ranks: None } => write!(f, "require {node}"),
+            Constraint::Require {
+                node,
+                ranks: Some(ranks),
+            } => write!(f, "require {node} at {ranks} ranks"),
+            Constraint::Avoid { node } => write!(f, "avoid {node}"),
+            Constraint::ExactlyOne { nodes } => write!(
+                f,
+                "exactly one of {{{}}}",
+                nodes
+                    .iter()
+                    .map(NodeRef::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Constraint::HeroTree { tree } => write!(f, "hero tree {tree}"),
+            Constraint::SpendAll { section } => write!(f, "spend all {section} points"),
+        }
+    }
+}
+
+impl fmt::Display for ConstraintSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for constraint in self.constraints.iter() {
+            writeln!(f, "{constraint}")?;
+        }
+        Ok(())
+    }
+}
+
+// case insensitive keyword prefix, keywords are single-space separated words
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = text.get(..keyword.len())?;
+    let rest = &text[keyword.len()..];
+    match head.eq_ignore_ascii_case(keyword) && (rest.is_empty() || rest.starts_with(' ')) {
+        true => Some(rest.trim_start()),
+        false => None,
+    }
+}
+
+fn parse_node_ref(text: &str) -> Result<NodeRef, String> {
+    let text = text.trim();
+    if let Some(name) = text.strip_prefix('"') {
+        return match name.strip_suffix('"') {
+            Some(name) if !name.is_empty() => Ok(NodeRef::Name(name.to_string())),
+            _ => Err(format!("unterminated or empty name {text}")),
+        };
+    }
+    match (text.parse::<usize>(), text.is_empty()) {
+        (Ok(id), _) => Ok(NodeRef::Id(id)),
+        (Err(_), true) => Err("expected a node id or name".to_string()),
+        (Err(_), false) => Ok(NodeRef::Name(text.to_string())),
+    }
+}
+
+// splits outside of quotes, names may contain `,`, `;` and `#`
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                items.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
+
+fn parse_require(rest: &str) -> Result<Constraint, String> {
+    // `<node> at <n> rank(s)`
+    let words = rest.split(' ').collect::<Vec<_>>();
+    let ranks = match words.as_slice() {
+        [.., at, ranks, unit]
+            if at.eq_ignore_ascii_case("at")
+                && (unit.eq_ignore_ascii_case("rank") || unit.eq_ignore_ascii_case("ranks")) =>
+        {
+            Some(
+                ranks
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid rank count {ranks}"))?,
+            )
+        }
+        _ => None,
+    };
+    let node = match ranks {
+        Some(_) => words[..words.len() - 3].join(" "),
+        None => rest.to_string(),
+    };
+
+    Ok(Constraint::Require {
+        node: parse_node_ref(&node)?,
+        ranks,
+    })
+}
+
+fn parse_constraint(text: &str) -> Result<Constraint, String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if let Some(rest) = strip_keyword(&text, "require") {
+        return parse_require(rest);
+    }
+    if let Some(rest) = strip_keyword(&text, "avoid") {
+        return Ok(Constraint::Avoid {
+            node: parse_node_ref(rest)?,
+        });
+    }
+    if let Some(rest) = strip_keyword(&text, "exactly one of") {
+        let Some(list) = rest.strip_prefix('{').and_then(|r| r.strip_suffix('}')) else {
+            return Err("expected a list like {B, C}".to_string());
+        };
+        let nodes = split_unquoted(list, ',')
+            .into_iter()
+            .map(parse_node_ref)
+            .collect::<Result<Vec<_>, _>>()?;
+        let constraint = Constraint::ExactlyOne { nodes };
+        return constraint.check().map(|_| constraint);
+    }
+    if let Some(rest) = strip_keyword(&text, "hero tree") {
+        return Ok(Constraint::HeroTree {
+            tree: parse_node_ref(rest)?,
+        });
+    }
+    if let Some(rest) = strip_keyword(&text, "spend all") {
+        let section = rest
+            .strip_suffix(" points")
+            .or_else(|| rest.strip_suffix(" point"))
+            .unwrap_or(rest);
+        return match section.to_lowercase().as_str() {
+            "class" => Ok(Constraint::SpendAll {
+                section: PointSection::Class,
+            }),
+            "spec" => Ok(Constraint::SpendAll {
+                section: PointSection::Spec,
+            }),
+            "hero" => Ok(Constraint::SpendAll {
+                section: PointSection::Hero,
+            }),
+            _ => Err(format!(
+                "unknown section {section}, expected class/spec/hero"
+            )),
+        };
+    }
+
+    Err(format!("unknown constraint {text}"))
+}
+
+impl FromStr for ConstraintSet {
+    type Err = ConstraintError;
+
+    // JSON arrays and the text form are both accepted
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('[') {
+            let set: ConstraintSet =
+                serde_json::from_str(s).map_err(|err| ConstraintError::Json(err.to_string()))?;
+            for (index, constraint) in set.constraints.iter().enumerate() {
+                constraint.check().map_err(|message| {
+                    ConstraintError::Json(format!("constraint {}: {message}", index + 1))
+                })?;
+            }
+            return Ok(set);
+        }
+
+        let mut constraints = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = split_unquoted(line, '#')[0];
+            for part in split_unquoted(line, ';')
+                .into_iter()
+                .filter(|part| !part.trim().is_empty())
+            {
+                constraints.push(parse_constraint(part).map_err(|message| {
+                    ConstraintError::Parse {
+                        line: index + 1,
+                        message,
+                    }
+                })?);
+            }
+        }
+        Ok(ConstraintSet { constraints })
+    }
+}
+
+impl ConstraintSet {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn resolve(
+        &self,
+        configuration: &TalentConfiguration,
+    ) -> Result<Vec<ResolvedConstraint>, ConstraintError> {
+        self.constraints
+            .iter()
+            .map(|constraint| constraint.resolve(configuration))
+            .collect()
+    }
+}
+
+impl Constraint {
+    // what the text form rejects while parsing, applied to JSON after it is read
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Constraint::ExactlyOne { nodes } if nodes.len() < 2 => {
+                Err("exactly one of needs at least two nodes".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        configuration: &TalentConfiguration,
+    ) -> Result<ResolvedConstraint, ConstraintError> {
+        Ok(match self {
+            Constraint::Require { node, ranks } => {
+                let target = resolve_node(node, configuration)?;
+                let max_ranks = max_ranks(target, configuration);
+                let ranks = ranks.unwrap_or(max_ranks);
+                if ranks == 0 || ranks > max_ranks {
+                    return Err(ConstraintError::InvalidRanks {
+                        node: node.clone(),
+                        ranks,
+                        max_ranks,
+                    });
+                }
+                ResolvedConstraint::Require { target, ranks }
+            }
+            Constraint::Avoid { node } => ResolvedConstraint::Avoid {
+                target: resolve_node(node, configuration)?,
+            },
+            Constraint::ExactlyOne { nodes } => ResolvedConstraint::ExactlyOne {
+                targets: nodes
+                    .iter()
+                    .map(|node| resolve_node(node, configuration))
+                    .collect::<Result<_, _>>()?,
+            },
+            Constraint::HeroTree { tree } => ResolvedConstraint::HeroTree {
+                trait_sub_tree_id: configuration
+                    .hero_trees()
+                    .into_iter()
+                    .find(|(id, name)| match tree {
+                        NodeRef::Id(tree_id) => id == tree_id,
+                        NodeRef::Name(tree_name) => name.eq_ignore_ascii_case(tree_name),
+                    })
+                    .map(|(id, _)| id)
+                    .ok_or_else(|| ConstraintError::UnknownHeroTree(tree.clone()))?,
+            },
+            Constraint::SpendAll { section } => ResolvedConstraint::SpendAll { section: *section },
+        })
+    }
+}
+
+// node ids first, then node names, then choice entry names
+fn resolve_node(
+    node: &NodeRef,
+    configuration: &TalentConfiguration,
+) -> Result<NodeTarget, ConstraintError> {
+    let nodes = configuration
+        .all_talents
+        .iter()
+        .map(|te| &te.trait_tree_node)
+        .filter(|ttn| ttn.id != 0 && ttn.node_type != TraitTreeNodeType::SubTree);
+
+    let resolvable = |ttn: &&TraitTreeNode| {
+        ttn.node_type != TraitTreeNodeType::SubTree && configuration.talent(ttn.id).is_some()
+    };
+    let matches = match node {
+        NodeRef::Id(id) => configuration
+            .talent(*id)
+            .map(|te| &te.trait_tree_node)
+            .filter(resolvable)
+            .map(|ttn| NodeTarget {
+                node_id: ttn.id,
+                entry_id: None,
+            })
+            .into_iter()
+            .collect::<Vec<_>>(),
+        NodeRef::Name(name) => {
+            let by_node = configuration
+                .trait_tree
+                .nodes_named(name)
+                .into_iter()
+                .filter(resolvable)
+                .map(|ttn| NodeTarget {
+                    node_id: ttn.id,
+                    entry_id: None,
+                })
+                .collect::<Vec<_>>();
+            match by_node.is_empty() {
+                false => by_node,
+                true => nodes
+                    .flat_map(|ttn| {
+                        ttn.entries
+                            .iter()
+                            .filter(|tte| {
+                                tte.name
+                                    .as_ref()
+                                    .is_some_and(|entry| entry.eq_ignore_ascii_case(name))
+                            })
+                            .map(|tte| NodeTarget {
+                                node_id: ttn.id,
+                                // only choice nodes have options worth telling apart
+                                entry_id: match ttn.node_type {
+                                    TraitTreeNodeType::Choice => tte.id,
+                                    _ => None,
+                                },
+                            })
+                    })
+                    .collect(),
+            }
+        }
+    };
+
+    match matches.as_slice() {
+        [] => Err(ConstraintError::UnknownNode(node.clone())),
+        [target] => Ok(*target),
+        _ => Err(ConstraintError::AmbiguousNode(node.clone())),
+    }
+}
+
+// entry ranks first, like `TalentEntry::max_ranks`
+fn max_ranks(target: NodeTarget, configuration: &TalentConfiguration) -> usize {
+    configuration
+        .talent(target.node_id)
+        .map(|te| {
+            let node = &te.trait_tree_node;
+            match node
+                .entries
+                .iter()
+                .find(|tte| target.entry_id.is_some() && tte.id == target.entry_id)
+            {
+                Some(tte) => tte.max_ranks.or(node.max_ranks).unwrap_or(1),
+                None => te.max_ranks(),
+            }
+        })
+        .unwrap_or(1)
+}
+
+impl TalentConfiguration {
+    // rank of the target, zero if a different choice option is taken
+    pub fn target_rank(&self, target: NodeTarget) -> usize {
+        self.talent(target.node_id)
+            .filter(|te| te.rank > 0)
+            .filter(|te| target.entry_id.is_none() || te.trait_tree_entry.id == target.entry_id)
+            .map_or(0, |te| te.rank)
+    }
+
+    pub fn satisfies(&self, constraint: &ResolvedConstraint) -> bool {
+        match constraint {
+            ResolvedConstraint::Require { target, ranks } => self.target_rank(*target) >= *ranks,
+            ResolvedConstraint::Avoid { target } => self.target_rank(*target) == 0,
+            ResolvedConstraint::ExactlyOne { targets } => {
+                targets
+                    .iter()
+                    .filter(|target| self.target_rank(**target) > 0)
+                    .count()
+                    == 1
+            }
+            ResolvedConstraint::HeroTree { trait_sub_tree_id } => {
+                self.subtrees.contains(trait_sub_tree_id)
+            }
+            ResolvedConstraint::SpendAll { section } => {
+                let sections = match section {
+                    PointSection::Class => vec![LayoutSection::Class],
+                    PointSection::Spec => vec![LayoutSection::Spec],
+                    PointSection::Hero => self
+                        .subtrees
+                        .iter()
+                        .map(|id| LayoutSection::Hero(*id))
+                        .collect(),
+                };
+                !sections.is_empty()
+                    && sections
+                        .into_iter()
+                        .all(|section| self.points_spent(section) >= self.point_budget(section))
+            }
+        }
+    }
+
+    pub fn satisfies_all(&self, constraints: &[ResolvedConstraint]) -> bool {
+        constraints
+            .iter()
+            .all(|constraint| self.satisfies(constraint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tree::empty_configuration;
+
+    fn name(name: &str) -> NodeRef {
+        NodeRef::Name(name.to_string())
+    }
+
+    fn parse_error(text: &str) -> String {
+        text.parse::<ConstraintSet>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn text_form() {
+        let text = "require 12345\nrequire \"Node A\" at 2 ranks; avoid D\n\n\
+                    exactly one of {B, \"C\"} # a comment\nhero tree X\nspend all spec points";
+        let expected = vec![
+            Constraint::Require {
+                node: NodeRef::Id(12345),
+                ranks: None,
+            },
+            Constraint::Require {
+                node: name("Node A"),
+                ranks: Some(2),
+            },
+            Constraint::Avoid { node: name("D") },
+            Constraint::ExactlyOne {
+                nodes: vec![name("B"), name("C")],
+            },
+            Constraint::HeroTree { tree: name("X") },
+            Constraint::SpendAll {
+                section: PointSection::Spec,
+            },
+        ];
+        let set = text.parse::<ConstraintSet>().unwrap();
+        assert_eq!(set.constraints, expected);
+        assert_eq!(set.to_string().parse::<ConstraintSet>().unwrap(), set);
+    }
+
+    #[test]
+    fn separators_inside_quotes_are_names() {
+        let set = "require \"Aim; #1\" # comment; avoid 2\navoid \"A, B\"; avoid 3"
+            .parse::<ConstraintSet>()
+            .unwrap();
+        assert_eq!(
+            set.constraints,
+            vec![
+                Constraint::Require {
+                    node: name("Aim; #1"),
+                    ranks: None,
+                },
+                Constraint::Avoid { node: name("A, B") },
+                Constraint::Avoid {
+                    node: NodeRef::Id(3),
+                },
+            ]
+        );
+        assert_eq!(
+            "exactly one of {\"A, B\", C}"
+                .parse::<ConstraintSet>()
+                .unwrap()
+                .constraints,
+            vec![Constraint::ExactlyOne {
+                nodes: vec![name("A, B"), name("C")],
+            }]
+        );
+    }
+
+    #[test]
+    fn text_errors_name_the_line() {
+        assert_eq!(
+            parse_error("require 1\nfrobnicate x"),
+            "Line 2: unknown constraint frobnicate x"
+        );
+        assert_eq!(
+            parse_error("exactly one of {A}"),
+            "Line 1: exactly one of needs at least two nodes"
+        );
+        assert_eq!(
+            parse_error("exactly one of A, B"),
+            "Line 1: expected a list like {B, C}"
+        );
+        assert_eq!(
+            parse_error("avoid 1; require \"A"),
+            "Line 1: unterminated or empty name \"A"
+        );
+        assert_eq!(
+            parse_error("require A at many ranks"),
+            "Line 1: invalid rank count many"
+        );
+        assert_eq!(
+            parse_error("\nspend all arcane points"),
+            "Line 2: unknown section arcane, expected class/spec/hero"
+        );
+    }
+
+    #[test]
+    fn json_form() {
+        let json = r#"[
+            { "type": "require", "node": "Node A", "ranks": 2 },
+            { "type": "avoid", "node": 12345 },
+            { "type": "exactly_one", "nodes": ["B", "C"] },
+            { "type": "hero_tree", "tree": "X" },
+            { "type": "spend_all", "section": "spec" }
+        ]"#;
+        let set = json.parse::<ConstraintSet>().unwrap();
+        assert_eq!(set.constraints.len(), 5);
+        assert_eq!(
+            set.constraints[1],
+            Constraint::Avoid {
+                node: NodeRef::Id(12345)
+            }
+        );
+        assert_eq!(set.to_json().parse::<ConstraintSet>().unwrap(), set);
+        assert_eq!(set.to_string().parse::<ConstraintSet>().unwrap(), set);
+    }
+
+    #[test]
+    fn json_errors() {
+        assert_eq!(
+            parse_error(
+                r#"[{ "type": "avoid", "node": 1 }, { "type": "exactly_one", "nodes": ["B"] }]"#
+            ),
+            "Invalid constraint JSON: constraint 2: exactly one of needs at least two nodes"
+        );
+        assert!(matches!(
+            r#"[{ "type": "ignore", "node": 1 }]"#.parse::<ConstraintSet>(),
+            Err(ConstraintError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn resolving_names() {
+        let configuration = empty_configuration();
+        let resolve = |text: &str| {
+            text.parse::<ConstraintSet>()
+                .unwrap()
+                .resolve(&configuration)
+        };
+
+        assert_eq!(
+            resolve("require \"right path\"; hero tree Warden; require 5"),
+            Ok(vec![
+                ResolvedConstraint::Require {
+                    target: NodeTarget {
+                        node_id: 3,
+                        entry_id: Some(31),
+                    },
+                    ranks: 1,
+                },
+                ResolvedConstraint::HeroTree {
+                    trait_sub_tree_id: 51,
+                },
+                ResolvedConstraint::Require {
+                    target: NodeTarget {
+                        node_id: 5,
+                        entry_id: None,
+                    },
+                    ranks: 2,
+                },
+            ])
+        );
+        assert_eq!(
+            resolve("avoid Nothing").unwrap_err().to_string(),
+            "No talent matches \"Nothing\""
+        );
+        assert_eq!(
+            resolve("hero tree Nobody").unwrap_err().to_string(),
+            "No hero tree matches \"Nobody\""
+        );
+        assert_eq!(
+            resolve("require \"Quick Shot\" at 3 ranks")
+                .unwrap_err()
+                .to_string(),
+            "\"Quick Shot\" has 2 ranks, cannot require 3"
+        );
+    }
+}
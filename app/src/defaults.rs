@@ -1,4 +1,7 @@
+use crate::solver::SolverSettings;
 use crate::talent_encoding::TalentEncoding;
+use crate::theme::TreeTheme;
+use crate::trait_tree::PointBudget;
 use crate::version::{ProductType, Version};
 use std::default::Default;
 
@@ -36,3 +39,29 @@ impl Default for Version {
         }
     }
 }
+
+impl Default for PointBudget {
+    fn default() -> Self {
+        PointBudget {
+            class: 34,
+            spec: 34,
+            hero: 13,
+        }
+    }
+}
+
+impl Default for TreeTheme {
+    fn default() -> Self {
+        TreeTheme::light()
+    }
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings {
+            max_steps: 500_000,
+            max_builds: 1,
+            per_hero_tree: false,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use leptos::prelude::*;
+use leptos::wasm_bindgen::{JsCast, JsValue};
+use leptos::web_sys::{
+    Blob, BlobPropertyBag, CanvasRenderingContext2d, Element, HtmlAnchorElement, HtmlCanvasElement,
+    HtmlImageElement, Url, XmlSerializer,
+};
+use thiserror::Error;
+use wasm_bindgen_futures::JsFuture;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+// png pixels per svg unit
+const PNG_SCALE: f64 = 2.0;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ExportError {
+    #[error("No talent tree is rendered")]
+    NothingRendered,
+    #[error("Could not load icon {0}")]
+    Icon(String),
+    #[error("Browser error: {0}")]
+    Browser(String),
+}
+
+impl From<JsValue> for ExportError {
+    fn from(value: JsValue) -> Self {
+        ExportError::Browser(format!("{value:?}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Svg,
+    Png,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "svg",
+            ExportFormat::Png => "png",
+        }
+    }
+}
+
+async fn icon_data_url(href: &str) -> Result<String, ExportError> {
+    let response = reqwasm::http::Request::get(href)
+        .send()
+        .await
+        .map_err(|_| ExportError::Icon(href.to_string()))?;
+    if !response.ok() {
+        return Err(ExportError::Icon(href.to_string()));
+    }
+    let mime = response
+        .headers()
+        .get("Content-Type")
+        .unwrap_or_else(|| "image/jpeg".to_string());
+    let bytes = response
+        .binary()
+        .await
+        .map_err(|_| ExportError::Icon(href.to_string()))?;
+
+    Ok(format!("data:{mime};base64,{}", STANDARD.encode(bytes)))
+}
+
+// width and height of the viewBox, the exported file shows what is currently in view
+fn view_box_size(svg: &Element) -> Option<(f64, f64)> {
+    let view_box = svg.get_attribute("viewBox")?;
+    let values = view_box
+        .split_whitespace()
+        .map(|value| value.parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match values.as_slice() {
+        [_, _, width, height] => Some((*width, *height)),
+        _ => None,
+    }
+}
+
+// copy of the rendered tree with its styles and icons embedded, so it renders without the app
+pub async fn standalone_svg(svg: &Element) -> Result<String, ExportError> {
+    let export = svg
+        .clone_node_with_deep(true)?
+        .dyn_into::<Element>()
+        .map_err(|_| ExportError::NothingRendered)?;
+    let (width, height) = view_box_size(svg).ok_or(ExportError::NothingRendered)?;
+
+    export.set_attribute("xmlns", SVG_NAMESPACE)?;
+    export.set_attribute("width", &width.to_string())?;
+    export.set_attribute("height", &height.to_string())?;
+    export.remove_attribute("style")?;
+
+    // the page stylesheet is not available to a standalone file
+    let font_family = svg
+        .get_attribute("font-family")
+        .unwrap_or_else(|| "sans-serif".to_string());
+    let style = document().create_element_ns(Some(SVG_NAMESPACE), "style")?;
+    style.set_text_content(Some(&format!("text {{ font-family: {font_family}; }}")));
+    export.prepend_with_node_1(&style)?;
+
+    // the same icon is usually drawn more than once
+    let mut icons: HashMap<String, String> = HashMap::new();
+    let images = export.query_selector_all("image")?;
+    for index in 0..images.length() {
+        let Some(image) = images
+            .get(index)
+            .and_then(|node| node.dyn_into::<Element>().ok())
+        else {
+            continue;
+        };
+        let Some(href) = image.get_attribute("href") else {
+            continue;
+        };
+        let data_url = match icons.get(&href) {
+            Some(data_url) => data_url.clone(),
+            None => {
+                let data_url = icon_data_url(&href).await?;
+                icons.insert(href, data_url.clone());
+                data_url
+            }
+        };
+        image.set_attribute("href", &data_url)?;
+    }
+
+    Ok(XmlSerializer::new()?.serialize_to_string(&export)?)
+}
+
+fn svg_object_url(svg: &str) -> Result<String, ExportError> {
+    let options = BlobPropertyBag::new();
+    options.set_type("image/svg+xml");
+    let blob = Blob::new_with_str_sequence_and_options(
+        &js_sys::Array::of1(&JsValue::from_str(svg)),
+        &options,
+    )?;
+    Ok(Url::create_object_url_with_blob(&blob)?)
+}
+
+fn download(url: &str, file_name: &str) -> Result<(), ExportError> {
+    let anchor = document()
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()
+        .map_err(|_| ExportError::Browser("could not create download link".to_string()))?;
+    anchor.set_href(url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Ok(())
+}
+
+async fn rasterize(svg_url: &str, (width, height): (f64, f64)) -> Result<String, ExportError> {
+    let image = HtmlImageElement::new()?;
+    image.set_src(svg_url);
+    JsFuture::from(image.decode()).await?;
+
+    let canvas = document()
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|_| ExportError::Browser("could not create canvas".to_string()))?;
+    canvas.set_width((width * PNG_SCALE).ceil() as u32);
+    canvas.set_height((height * PNG_SCALE).ceil() as u32);
+    let context = canvas
+        .get_context("2d")?
+        .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+        .ok_or_else(|| ExportError::Browser("canvas 2d context unavailable".to_string()))?;
+
+    // transparent pixels turn black in some spreadsheet and image viewers
+    context.set_fill_style_str("white");
+    context.fill_rect(0.0, 0.0, width * PNG_SCALE, height * PNG_SCALE);
+    context.draw_image_with_html_image_element_and_dw_and_dh(
+        &image,
+        0.0,
+        0.0,
+        width * PNG_SCALE,
+        height * PNG_SCALE,
+    )?;
+
+    Ok(canvas.to_data_url_with_type("image/png")?)
+}
+
+pub async fn export_tree(
+    svg: &Element,
+    format: ExportFormat,
+    name: &str,
+) -> Result<(), ExportError> {
+    let standalone = standalone_svg(svg).await?;
+    let size = view_box_size(svg).ok_or(ExportError::NothingRendered)?;
+    let svg_url = svg_object_url(&standalone)?;
+    let file_name = format!("{name}.{}", format.extension());
+
+    let result = match format {
+        ExportFormat::Svg => download(&svg_url, &file_name),
+        ExportFormat::Png => match rasterize(&svg_url, size).await {
+            Ok(png_url) => download(&png_url, &file_name),
+            Err(err) => Err(err),
+        },
+    };
+    // the browser may still be reading the blob when the download starts
+    set_timeout(
+        move || {
+            let _ = Url::revoke_object_url(&svg_url);
+        },
+        Duration::from_secs(1),
+    );
+    result
+}
+
+// e.g. `death-knight-blood`
+pub fn export_name(class_name: &str, spec_name: &str) -> String {
+    format!("{class_name} {spec_name}")
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
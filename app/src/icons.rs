@@ -0,0 +1,30 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// maps spell ids to icon file names in `/icons/`, e.g. `{ "12345": "spell_fire_fireball.jpg" }`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconManifest(pub HashMap<usize, String>);
+
+impl IconManifest {
+    pub fn icon_url(&self, spell_id: Option<usize>) -> Option<String> {
+        spell_id
+            .and_then(|spell_id| self.0.get(&spell_id))
+            .map(|file| format!("/icons/{file}"))
+    }
+}
+
+// fetched once by `HomePage` and shared through context
+pub type IconData = LocalResource<Result<IconManifest, Error>>;
+
+pub fn use_icon_data() -> IconData {
+    use_context::<IconData>().expect("Must have a parent that provides an `IconData` context.")
+}
+
+pub async fn fetch_icon_manifest() -> Result<IconManifest, Error> {
+    Ok(reqwasm::http::Request::get("/icons/manifest.json")
+        .send()
+        .await?
+        .json()
+        .await?)
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use crate::talent_configuration::TalentConfiguration;
+use crate::trait_tree::{NodeSource, TraitTreeNode, TraitTreeNodeType};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutSettings {
+    // data units per svg unit
+    pub scale_factor: i32,
+    // svg units between node tiers, when the data has them
+    pub row_height: i32,
+    // svg units between the class, hero and spec sections
+    pub section_gap: i32,
+    // svg units between hero trees drawn side by side
+    pub hero_tree_gap: i32,
+    // svg units around the whole tree, leaves room for node outlines and rank badges
+    pub padding: i32,
+    // svg units above each section for its point counter
+    pub header_height: i32,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            scale_factor: 15,
+            row_height: 40,
+            section_gap: 40,
+            hero_tree_gap: 20,
+            padding: 20,
+            header_height: 16,
+        }
+    }
+}
+
+impl LayoutSettings {
+    pub fn node_position(&self, node: &TraitTreeNode) -> (i32, i32) {
+        (
+            node.pos_x / self.scale_factor.max(1),
+            self.row_y(node.node_tier, node.pos_y),
+        )
+    }
+
+    // tiers from the data are spaced evenly, otherwise the data position is kept
+    pub fn row_y(&self, node_tier: Option<usize>, pos_y: i32) -> i32 {
+        match node_tier {
+            Some(tier) => tier as i32 * self.row_height,
+            None => pos_y / self.scale_factor.max(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LayoutSection {
+    Class,
+    Spec,
+    Hero(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl Bounds {
+    fn from_points(points: impl Iterator<Item = (i32, i32)>) -> Option<Self> {
+        points.fold(None, |bounds: Option<Bounds>, (x, y)| {
+            Some(match bounds {
+                Some(b) => Bounds {
+                    min_x: b.min_x.min(x),
+                    min_y: b.min_y.min(y),
+                    max_x: b.max_x.max(x),
+                    max_y: b.max_y.max(y),
+                },
+                None => Bounds {
+                    min_x: x,
+                    min_y: y,
+                    max_x: x,
+                    max_y: y,
+                },
+            })
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max_y - self.min_y
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeLayout {
+    pub offsets: HashMap<LayoutSection, (i32, i32)>,
+    // placed bounds of each section, without its header
+    pub sections: HashMap<LayoutSection, Bounds>,
+    pub bounds: Bounds,
+}
+
+impl TreeLayout {
+    pub fn position(&self, section: LayoutSection, (x, y): (i32, i32)) -> (i32, i32) {
+        let (x_offset, y_offset) = self.offsets.get(&section).copied().unwrap_or((0, 0));
+        (x + x_offset, y + y_offset)
+    }
+}
+
+// zoom and pan of the rendered tree, pan is a fraction of the full tree size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub zoom: f64,
+    pub pan_x: f64,
+    pub pan_y: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan_x: 0.0,
+            pan_y: 0.0,
+        }
+    }
+}
+
+impl Viewport {
+    const MAX_ZOOM: f64 = 8.0;
+
+    fn clamped(self) -> Self {
+        let zoom = self.zoom.clamp(1.0, Self::MAX_ZOOM);
+        let max_pan = 1.0 - 1.0 / zoom;
+        Self {
+            zoom,
+            pan_x: self.pan_x.clamp(0.0, max_pan),
+            pan_y: self.pan_y.clamp(0.0, max_pan),
+        }
+    }
+
+    // wheel and drag are only taken from the page once the tree is zoomed in
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom > 1.0
+    }
+
+    // zooms around the center of the current view
+    pub fn zoom_by(self, factor: f64) -> Self {
+        let zoom = (self.zoom * factor).clamp(1.0, Self::MAX_ZOOM);
+        let center_x = self.pan_x + 0.5 / self.zoom;
+        let center_y = self.pan_y + 0.5 / self.zoom;
+        Self {
+            zoom,
+            pan_x: center_x - 0.5 / zoom,
+            pan_y: center_y - 0.5 / zoom,
+        }
+        .clamped()
+    }
+
+    pub fn pan_by(self, dx: f64, dy: f64) -> Self {
+        Self {
+            pan_x: self.pan_x + dx / self.zoom,
+            pan_y: self.pan_y + dy / self.zoom,
+            ..self
+        }
+        .clamped()
+    }
+
+    pub fn view_box(&self, bounds: &Bounds) -> String {
+        let (width, height) = (bounds.width() as f64, bounds.height() as f64);
+        format!(
+            "{:.2} {:.2} {:.2} {:.2}",
+            bounds.min_x as f64 + self.pan_x * width,
+            bounds.min_y as f64 + self.pan_y * height,
+            width / self.zoom,
+            height / self.zoom,
+        )
+    }
+}
+
+impl TalentConfiguration {
+    pub fn layout_section(&self, node: &TraitTreeNode) -> LayoutSection {
+        match (node.trait_sub_tree_id, node.node_source) {
+            (Some(tst_id), _) => LayoutSection::Hero(tst_id),
+            (None, Some(NodeSource::Spec)) => LayoutSection::Spec,
+            (None, Some(_)) => LayoutSection::Class,
+            // trees that were not annotated
+            (None, None)
+                if self
+                    .trait_tree
+                    .spec_nodes
+                    .iter()
+                    .any(|ttn| ttn.id == node.id) =>
+            {
+                LayoutSection::Spec
+            }
+            (None, None) => LayoutSection::Class,
+        }
+    }
+
+    fn section_bounds<'a>(
+        &self,
+        nodes: impl Iterator<Item = &'a TraitTreeNode>,
+        settings: &LayoutSettings,
+    ) -> Option<Bounds> {
+        Bounds::from_points(
+            nodes
+                .filter(|node| node.id != 0 && node.node_type != TraitTreeNodeType::SubTree)
+                .map(|node| settings.node_position(node)),
+        )
+    }
+
+    // class, hero and spec sections are placed left to right, hero trees centered vertically
+    pub fn compute_layout(&self, hero_trees: &[usize], settings: &LayoutSettings) -> TreeLayout {
+        let class = self
+            .section_bounds(self.trait_tree.class_nodes.iter(), settings)
+            .map(|bounds| (LayoutSection::Class, bounds));
+        let spec = self
+            .section_bounds(self.trait_tree.spec_nodes.iter(), settings)
+            .map(|bounds| (LayoutSection::Spec, bounds));
+        let heroes = hero_trees
+            .iter()
+            .filter_map(|tst_id| {
+                self.section_bounds(
+                    self.trait_tree
+                        .hero_nodes
+                        .iter()
+                        .filter(|node| node.trait_sub_tree_id == Some(*tst_id)),
+                    settings,
+                )
+                .map(|bounds| (LayoutSection::Hero(*tst_id), bounds))
+            })
+            .collect::<Vec<_>>();
+
+        let height = class
+            .iter()
+            .chain(spec.iter())
+            .chain(heroes.iter())
+            .map(|(_, bounds)| bounds.height())
+            .max()
+            .unwrap_or(0);
+
+        let mut offsets = HashMap::new();
+        let mut sections = HashMap::new();
+        let top = settings.padding + settings.header_height;
+        let mut cursor = settings.padding;
+        let mut right = settings.padding;
+        let mut place = |(section, bounds): (LayoutSection, Bounds), gap: i32| {
+            let y_offset = match section {
+                LayoutSection::Hero(_) => (height - bounds.height()) / 2,
+                _ => 0,
+            };
+            offsets.insert(
+                section,
+                (cursor - bounds.min_x, top + y_offset - bounds.min_y),
+            );
+            sections.insert(
+                section,
+                Bounds {
+                    min_x: cursor,
+                    min_y: top + y_offset,
+                    max_x: cursor + bounds.width(),
+                    max_y: top + y_offset + bounds.height(),
+                },
+            );
+            right = cursor + bounds.width();
+            cursor = right + gap;
+        };
+
+        if let Some(class) = class {
+            place(class, settings.section_gap);
+        }
+        let hero_count = heroes.len();
+        for (index, hero) in heroes.into_iter().enumerate() {
+            match index + 1 == hero_count {
+                true => place(hero, settings.section_gap),
+                false => place(hero, settings.hero_tree_gap),
+            }
+        }
+        if let Some(spec) = spec {
+            place(spec, 0);
+        }
+
+        TreeLayout {
+            offsets,
+            sections,
+            bounds: Bounds {
+                min_x: 0,
+                min_y: 0,
+                max_x: right + settings.padding,
+                max_y: top + height + settings.padding,
+            },
+        }
+    }
+}
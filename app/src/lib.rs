@@ -2,21 +2,44 @@ use leptos::prelude::*;
 use leptos_meta::{provide_meta_context, Title};
 use leptos_router::{
     components::{Route, Router, Routes},
+    hooks::use_query_map,
     StaticSegment,
 };
-use thaw::ConfigProvider;
+use thaw::{ConfigProvider, Theme};
 
+use crate::canonical::DedupeView;
 use crate::configuration_generation::TalentConfigurationGeneration;
+use crate::icons::{fetch_icon_manifest, IconData};
+use crate::loadouts::LoadoutsView;
+use crate::share::ShareLinkView;
 use crate::talent_configuration::TalentConfigView;
 use crate::talent_encoding::TalentEncoding;
+use crate::theme::{ThemePreset, ThemeSwitcher, TreeTheme};
+use crate::trait_tree::{fetch_trait_trees, TraitTreeData};
+use crate::variants::BuildVariantsView;
 use crate::version::VersionView;
 
+mod canonical;
 mod configuration_generation;
+mod constraints;
 mod defaults;
+mod export;
+mod icons;
+mod layout;
+mod loadouts;
+mod search;
+mod share;
+mod solver;
 mod talent_configuration;
 mod talent_encoding;
+mod talent_path;
+#[cfg(test)]
+mod test_tree;
+mod theme;
 mod trait_tree;
+mod variants;
 mod version;
+mod weight_import;
 
 #[component]
 pub fn App() -> impl IntoView {
@@ -43,13 +66,37 @@ pub fn App() -> impl IntoView {
 fn HomePage() -> impl IntoView {
     let (talent_encoding, set_talent_encoding) = signal(TalentEncoding::default());
     provide_context(set_talent_encoding);
+    provide_context::<TraitTreeData>(LocalResource::new(fetch_trait_trees));
+    provide_context::<IconData>(LocalResource::new(fetch_icon_manifest));
+    // short links redirect to `/?talents=<string>`
+    let (talent_str, set_talent_str) = signal(
+        use_query_map()
+            .with_untracked(|query| query.get("talents"))
+            .unwrap_or("CwQAAAAAAAAAAAAAAAAAAAAAAAAAAgZZzYGzYWmx2YmZMAAAAAAAWAxMDmhZsYGsNzMjZMMzsMLm22sNbzMD2AAgNEAAAz2s0MzMLMYD".to_string()),
+    );
+
+    let theme_preset = RwSignal::new(ThemePreset::default());
+    let thaw_theme = RwSignal::new(Theme::light());
+    Effect::new(move |_| thaw_theme.set(theme_preset.get().thaw_theme()));
+    let tree_theme = Memo::new(move |_| theme_preset.get().tree_theme());
+    provide_context(Signal::<TreeTheme>::from(tree_theme));
 
     view! {
-        <ConfigProvider>
-            <div>{move || format!("{}", talent_encoding.get())}</div>
-            <VersionView />
-            <TalentConfigView talent_encoding />
-            <TalentConfigurationGeneration talent_encoding />
+        <ConfigProvider theme=thaw_theme>
+            <div
+                class=move || format!("tree-theme tree-theme-{}", theme_preset.get().name())
+                style=move || tree_theme.with(TreeTheme::css_vars)
+            >
+                <ThemeSwitcher theme_preset />
+                <div>{move || format!("{}", talent_encoding.get())}</div>
+                <VersionView />
+                <LoadoutsView talent_encoding talent_str set_talent_str />
+                <ShareLinkView talent_encoding talent_str />
+                <TalentConfigView talent_encoding talent_str set_talent_str />
+                <BuildVariantsView talent_encoding talent_str set_talent_str />
+                <DedupeView talent_encoding />
+                <TalentConfigurationGeneration talent_encoding talent_str set_talent_str />
+            </div>
         </ConfigProvider>
     }
 }
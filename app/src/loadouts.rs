@@ -0,0 +1,271 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use leptos::web_sys::Storage;
+
+use crate::talent_configuration::TalentConfiguration;
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::use_trait_tree_data;
+
+const LOADOUTS_STORAGE_KEY: &str = "talent-tree-gen.loadouts";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LoadoutError {
+    #[error("Browser local storage is not available")]
+    StorageUnavailable,
+    #[error("Could not read or write saved loadouts: {0}")]
+    Serialization(String),
+    #[error("A loadout needs a name")]
+    MissingName,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Loadout {
+    pub name: String,
+    pub spec: usize,
+    pub class_name: String,
+    pub spec_name: String,
+    pub string: String,
+    pub talent_encoding: TalentEncoding,
+    pub notes: String,
+    pub tags: Vec<String>,
+}
+
+impl Loadout {
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty()
+            || self.name.to_lowercase().contains(&filter)
+            || self.notes.to_lowercase().contains(&filter)
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&filter))
+    }
+}
+
+fn local_storage() -> Result<Storage, LoadoutError> {
+    window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .ok_or(LoadoutError::StorageUnavailable)
+}
+
+pub fn load_loadouts() -> Result<Vec<Loadout>, LoadoutError> {
+    match local_storage()?.get_item(LOADOUTS_STORAGE_KEY) {
+        Ok(Some(json)) => {
+            serde_json::from_str(&json).map_err(|err| LoadoutError::Serialization(err.to_string()))
+        }
+        Ok(None) => Ok(Vec::new()),
+        Err(_) => Err(LoadoutError::StorageUnavailable),
+    }
+}
+
+pub fn store_loadouts(loadouts: &[Loadout]) -> Result<(), LoadoutError> {
+    let json = serde_json::to_string(loadouts)
+        .map_err(|err| LoadoutError::Serialization(err.to_string()))?;
+    local_storage()?
+        .set_item(LOADOUTS_STORAGE_KEY, &json)
+        .map_err(|_| LoadoutError::StorageUnavailable)
+}
+
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[component]
+pub fn LoadoutsView(
+    talent_encoding: ReadSignal<TalentEncoding>,
+    talent_str: ReadSignal<String>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let set_talent_encoding = use_context::<WriteSignal<TalentEncoding>>()
+        .expect("Must have a parent that provides a `WriteSignal<TalentEncoding>` context.");
+
+    let trait_tree_data = use_trait_tree_data();
+
+    let (error, set_error) = signal(None::<String>);
+    let loadouts = RwSignal::new(load_loadouts().unwrap_or_else(|err| {
+        set_error.set(Some(err.to_string()));
+        Vec::new()
+    }));
+
+    let (name, set_name) = signal(String::new());
+    let (notes, set_notes) = signal(String::new());
+    let (tags, set_tags) = signal(String::new());
+    let (filter, set_filter) = signal(String::new());
+    let (spec_filter, set_spec_filter) = signal(None::<usize>);
+
+    let persist = move |updated: Vec<Loadout>| match store_loadouts(&updated) {
+        Ok(()) => {
+            set_error.set(None);
+            loadouts.set(updated);
+        }
+        Err(err) => set_error.set(Some(err.to_string())),
+    };
+
+    let save = move |_| {
+        let name = name.get().trim().to_string();
+        if name.is_empty() {
+            set_error.set(Some(LoadoutError::MissingName.to_string()));
+            return;
+        }
+        let Some(Ok(trait_trees)) = trait_tree_data.get() else {
+            set_error.set(Some("Talent data is not loaded yet".to_string()));
+            return;
+        };
+        let configuration = match TalentConfiguration::new_from_str(
+            &talent_str.get(),
+            talent_encoding.get(),
+            trait_trees,
+        ) {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                set_error.set(Some(err.to_string()));
+                return;
+            }
+        };
+
+        let loadout = Loadout {
+            name,
+            spec: configuration.spec,
+            class_name: configuration.trait_tree.class_name.clone(),
+            spec_name: configuration.trait_tree.spec_name.clone(),
+            string: configuration.string.clone(),
+            talent_encoding: talent_encoding.get(),
+            notes: notes.get(),
+            tags: parse_tags(&tags.get()),
+        };
+
+        // saving under an existing name for the same spec overwrites that loadout
+        let mut updated = loadouts.get();
+        match updated
+            .iter_mut()
+            .find(|l| l.spec == loadout.spec && l.name == loadout.name)
+        {
+            Some(existing) => *existing = loadout,
+            None => updated.push(loadout),
+        }
+        persist(updated);
+    };
+
+    let load = move |loadout: &Loadout| {
+        set_talent_encoding.set(loadout.talent_encoding.clone());
+        set_talent_str.set(loadout.string.clone());
+        set_name.set(loadout.name.clone());
+        set_notes.set(loadout.notes.clone());
+        set_tags.set(loadout.tags.join(", "));
+    };
+
+    let delete = move |loadout: &Loadout| {
+        let mut updated = loadouts.get();
+        updated.retain(|l| !(l.spec == loadout.spec && l.name == loadout.name));
+        persist(updated);
+    };
+
+    let specs = move || {
+        let mut specs = loadouts.with(|loadouts| {
+            loadouts
+                .iter()
+                .map(|l| (l.spec, format!("{} {}", l.spec_name, l.class_name)))
+                .collect::<Vec<_>>()
+        });
+        specs.sort();
+        specs.dedup();
+        specs
+    };
+
+    let filtered = move || {
+        loadouts.with(|loadouts| {
+            loadouts
+                .iter()
+                .filter(|l| spec_filter.get().is_none_or(|spec| spec == l.spec))
+                .filter(|l| l.matches(&filter.get()))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    };
+
+    view! {
+        <div class="loadouts">
+            <div>
+                <input
+                    type="text"
+                    placeholder="Name"
+                    on:input:target=move |tag| set_name.set(tag.target().value())
+                    prop:value=name
+                />
+                <input
+                    type="text"
+                    placeholder="Tags (comma separated)"
+                    on:input:target=move |tag| set_tags.set(tag.target().value())
+                    prop:value=tags
+                />
+                <textarea
+                    placeholder="Notes"
+                    on:input:target=move |tag| set_notes.set(tag.target().value())
+                    prop:value=notes
+                />
+                <button on:click=save>"Save loadout"</button>
+            </div>
+            {move || error.get().map(|err| view! { <div class="loadouts-error">{err}</div> })}
+            <div>
+                <input
+                    type="text"
+                    placeholder="Filter"
+                    on:input:target=move |tag| set_filter.set(tag.target().value())
+                    prop:value=filter
+                />
+                <select on:input:target=move |tag| {
+                    set_spec_filter.set(tag.target().value().parse::<usize>().ok())
+                }>
+                    <option value="all">"All specs"</option>
+                    {move || {
+                        specs()
+                            .into_iter()
+                            .map(|(spec, label)| view! { <option value=spec>{label}</option> })
+                            .collect::<Vec<_>>()
+                    }}
+                </select>
+            </div>
+            <ul>
+                <For
+                    each=filtered
+                    key=|loadout| (loadout.spec, loadout.name.clone(), loadout.string.clone())
+                    let(loadout)
+                >
+                    {
+                        let for_load = loadout.clone();
+                        let for_delete = loadout.clone();
+                        view! {
+                            <li>
+                                <span>{loadout.name.clone()}</span>
+                                " "
+                                <span>
+                                    {format!(
+                                        "{} {} ({})",
+                                        loadout.spec_name,
+                                        loadout.class_name,
+                                        loadout.talent_encoding,
+                                    )}
+                                </span>
+                                {(!loadout.tags.is_empty())
+                                    .then(|| {
+                                        view! { <span>{format!(" [{}]", loadout.tags.join(", "))}</span> }
+                                    })}
+                                <button on:click=move |_| load(&for_load)>"Load"</button>
+                                <button on:click=move |_| delete(&for_delete)>"Delete"</button>
+                                <div>{loadout.notes.clone()}</div>
+                            </li>
+                        }
+                    }
+                </For>
+            </ul>
+        </div>
+    }
+}
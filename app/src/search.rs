@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use leptos::prelude::*;
+
+use crate::talent_configuration::{Highlight, TalentConfiguration, TalentConfigurationError};
+use crate::trait_tree::{TraitTree, TraitTreeNode, TraitTreeNodeType};
+
+/*
+ * Names match fuzzily, every character of the query in order with runs and word starts counting
+ * more, so "ff" finds "Flame Flurry" ahead of "Fire Staff". A query of digits matches spell ids
+ * starting with it instead.
+ */
+
+const MATCHES_SHOWN: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub node_id: usize,
+    // the node name, entry name or spell id that matched
+    pub label: String,
+    pub score: usize,
+}
+
+// None unless every query character is found in order
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|t| *t == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+fn spell_score(query: &str, spell_id: usize) -> Option<usize> {
+    let spell_id = spell_id.to_string();
+    match spell_id.starts_with(query) {
+        true if spell_id == query => Some(2 * query.len() + 1),
+        true => Some(query.len()),
+        false => None,
+    }
+}
+
+impl TraitTree {
+    // best match per node, best first
+    pub fn search(&self, query: &str) -> Vec<SearchMatch> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let by_spell = query.chars().all(|c| c.is_ascii_digit());
+
+        let mut matches = Vec::new();
+        for node in self
+            .nodes()
+            .filter(|node| node.id != 0 && node.node_type != TraitTreeNodeType::SubTree)
+        {
+            let mut candidates = Vec::new();
+            match by_spell {
+                true => candidates.extend(node.entries.iter().filter_map(|entry| {
+                    let spell_id = entry.spell_id?;
+                    Some((spell_score(query, spell_id)?, format!("spell {spell_id}")))
+                })),
+                false => {
+                    let names = std::iter::once(&node.name)
+                        .chain(node.entries.iter().filter_map(|entry| entry.name.as_ref()));
+                    candidates.extend(
+                        names.filter_map(|name| Some((fuzzy_score(query, name)?, name.clone()))),
+                    );
+                }
+            }
+            if let Some((score, label)) = candidates.into_iter().max_by_key(|(score, _)| *score) {
+                matches.push(SearchMatch {
+                    node_id: node.id,
+                    label,
+                    score,
+                });
+            }
+        }
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.label.cmp(&b.label)));
+        matches
+    }
+
+    // fewest nodes from an entry node down to the node, the node last
+    pub fn prerequisite_path(&self, node_id: usize) -> Vec<usize> {
+        let is_start =
+            |node: &TraitTreeNode| node.entry_node.unwrap_or(false) || node.prev.is_empty();
+
+        // breadth first up the prev edges, remembering the child each node was reached from
+        let mut reached_from = HashMap::from([(node_id, None)]);
+        let mut queue = VecDeque::from([node_id]);
+        while let Some(id) = queue.pop_front() {
+            let Some(node) = self.node(id) else {
+                continue;
+            };
+            if is_start(node) {
+                let mut path = vec![id];
+                while let Some(Some(child)) = reached_from.get(path.last().unwrap()) {
+                    path.push(*child);
+                }
+                return path;
+            }
+            for prev in &node.prev {
+                if !reached_from.contains_key(prev) {
+                    reached_from.insert(*prev, Some(id));
+                    queue.push_back(*prev);
+                }
+            }
+        }
+        Vec::new()
+    }
+}
+
+#[component]
+pub fn TalentSearch(
+    talent_configuration: Memo<Result<TalentConfiguration, TalentConfigurationError>>,
+    highlight: RwSignal<Highlight>,
+    // the clicked match, the path is shown for the best match until then
+    selected: RwSignal<Option<usize>>,
+) -> impl IntoView {
+    let (query, set_query) = signal(String::new());
+
+    let matches = Memo::new(move |_| {
+        talent_configuration.with(|config| match config {
+            Ok(conf) => query.with(|query| conf.trait_tree.search(query)),
+            Err(_) => Vec::new(),
+        })
+    });
+    let path = Memo::new(move |_| {
+        let node_id = selected
+            .get()
+            .or_else(|| matches.with(|matches| matches.first().map(|m| m.node_id)));
+        talent_configuration.with(|config| match (config, node_id) {
+            (Ok(conf), Some(node_id)) => conf
+                .trait_tree
+                .prerequisite_path(node_id)
+                .into_iter()
+                .filter_map(|id| conf.trait_tree.node(id))
+                .map(|node| (node.id, node.name.clone()))
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        })
+    });
+
+    let set_highlight = move || {
+        highlight.set(Highlight {
+            nodes: matches.with_untracked(|matches| matches.iter().map(|m| m.node_id).collect()),
+            path: path
+                .with_untracked(|path| path.iter().map(|(id, _)| *id).collect::<HashSet<_>>()),
+        })
+    };
+
+    view! {
+        <div class="talent-search">
+            <input
+                type="search"
+                placeholder="Search talents by name or spell id"
+                prop:value=query
+                on:input:target=move |tag| {
+                    set_query.set(tag.target().value());
+                    selected.set(None);
+                    set_highlight();
+                }
+            />
+            <ul>
+                {move || {
+                    matches
+                        .get()
+                        .into_iter()
+                        .take(MATCHES_SHOWN)
+                        .map(|m| {
+                            let node_id = m.node_id;
+                            view! {
+                                <li>
+                                    <button on:click=move |_| {
+                                        selected.set(Some(node_id));
+                                        set_highlight();
+                                    }>{m.label}</button>
+                                </li>
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                }}
+            </ul>
+            {move || {
+                let path = path.get();
+                (!path.is_empty())
+                    .then(|| {
+                        let names = path.into_iter().map(|(_, name)| name).collect::<Vec<_>>();
+                        view! { <div>{format!("Path: {}", names.join(" → "))}</div> }
+                    })
+            }}
+        </div>
+    }
+}
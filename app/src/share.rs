@@ -0,0 +1,71 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::talent_configuration::TalentConfiguration;
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::use_trait_tree_data;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CreateBuild {
+    string: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CreatedBuild {
+    id: String,
+}
+
+async fn create_short_link(string: String) -> Result<String, Error> {
+    let created: CreatedBuild = reqwasm::http::Request::post("/api/builds")
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(&CreateBuild { string })?)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let origin = window().location().origin().unwrap_or_default();
+
+    Ok(format!("{origin}/b/{}", created.id))
+}
+
+#[component]
+pub fn ShareLinkView(
+    talent_encoding: ReadSignal<TalentEncoding>,
+    talent_str: ReadSignal<String>,
+) -> impl IntoView {
+    let trait_tree_data = use_trait_tree_data();
+    let share = Action::new_local(|string: &String| create_short_link(string.clone()));
+    let (error, set_error) = signal(None::<String>);
+
+    // the canonical string is sent, so every way of writing a build shares one link
+    let create = move |_| {
+        let Some(Ok(trait_trees)) = trait_tree_data.get() else {
+            set_error.set(Some("Talent data is not loaded yet".to_string()));
+            return;
+        };
+        let config = talent_encoding.get();
+        match TalentConfiguration::new_from_str(&talent_str.get(), config.clone(), trait_trees) {
+            Ok(configuration) => {
+                set_error.set(None);
+                share.dispatch(configuration.canonical(&config).string);
+            }
+            Err(err) => set_error.set(Some(err.to_string())),
+        }
+    };
+
+    view! {
+        <div>
+            <button on:click=create>"Create short link"</button>
+            {move || error.get().map(|err| view! { <span>{err}</span> })}
+            {move || {
+                share
+                    .value()
+                    .get()
+                    .map(|link| match link {
+                        Ok(link) => view! { <a href=link.clone()>{link.clone()}</a> }.into_any(),
+                        Err(err) => view! { <span>{err.to_string()}</span> }.into_any(),
+                    })
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::constraints::{NodeTarget, ResolvedConstraint};
+use crate::layout::LayoutSection;
+use crate::talent_configuration::TalentConfiguration;
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::TraitTreeNodeType;
+
+/*
+ * Branch and bound over one integer rank variable per node, plus the chosen entry of choice nodes.
+ * Nodes are visited section by section in row order, so when a node is decided its parents and
+ * every row above it already are:
+
+rank > 0        =>  entry node, no parents, or a maxed parent
+rank > 0        =>  points spent on rows above in the section >= req_points
+sum(section)    <=  section budget
+choice nodes    =   one entry
+hero nodes      =   only in the hero tree being searched, one search per hero tree
+
+ * The bound is the best marginal rank values still available per section, ignoring connectivity.
+ */
+
+// value of each rank of a talent, keyed by node and optionally the chosen choice entry
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Weights {
+    values: HashMap<NodeTarget, Vec<f64>>,
+}
+
+impl Weights {
+    // a single value applies to every rank, no values clears the weight
+    pub fn set(&mut self, target: NodeTarget, per_rank: Vec<f64>) {
+        match per_rank.is_empty() {
+            true => self.values.remove(&target),
+            false => self.values.insert(target, per_rank),
+        };
+    }
+
+    pub fn get(&self, target: &NodeTarget) -> Option<&[f64]> {
+        self.values.get(target).map(Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // value of the `rank`th point, entry specific values win over node wide ones
+    pub fn marginal(&self, node_id: usize, entry_id: Option<usize>, rank: usize) -> f64 {
+        let values = self
+            .values
+            .get(&NodeTarget { node_id, entry_id })
+            .filter(|_| entry_id.is_some())
+            .or_else(|| {
+                self.values.get(&NodeTarget {
+                    node_id,
+                    entry_id: None,
+                })
+            });
+        match values.map(Vec::as_slice) {
+            Some([value]) => *value,
+            Some(values) => values.get(rank.max(1) - 1).copied().unwrap_or(0.0),
+            None => 0.0,
+        }
+    }
+
+    pub fn value(&self, node_id: usize, entry_id: Option<usize>, rank: usize) -> f64 {
+        (1..=rank)
+            .map(|rank| self.marginal(node_id, entry_id, rank))
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolverSettings {
+    // assignments tried before giving up, keeps the page responsive in wasm
+    pub max_steps: usize,
+    pub max_builds: usize,
+    // max_builds for every hero tree instead of overall
+    pub per_hero_tree: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolvedBuild {
+    pub configuration: TalentConfiguration,
+    pub score: f64,
+    pub hero_tree: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolverOutcome {
+    // best first, grouped by hero tree with per_hero_tree
+    pub builds: Vec<SolvedBuild>,
+    // false if the step limit cut the search short, better builds may exist
+    pub complete: bool,
+    pub steps: usize,
+}
+
+// one decision per node
+struct Variable {
+    node_id: usize,
+    section: usize,
+    // (entry id, max ranks) per choice option, a single option for other nodes
+    options: Vec<(Option<usize>, usize)>,
+    // variable indices of parents in this search
+    parents: Vec<usize>,
+    unconditional: bool,
+    req_points: usize,
+    // first variable of the same row in the same section
+    row_start: usize,
+    min_rank: usize,
+    required_entry: Option<usize>,
+    avoided_entries: Vec<Option<usize>>,
+    max_rank: usize,
+}
+
+struct Search<'a> {
+    variables: Vec<Variable>,
+    budgets: Vec<usize>,
+    spend_all: Vec<bool>,
+    // per variable and section, ranks still available from that variable on
+    remaining_ranks: Vec<Vec<usize>>,
+    // per variable and section, prefix sums of the best remaining marginal values
+    remaining_values: Vec<Vec<Vec<f64>>>,
+    // (variable, entry) groups of which exactly one has to be taken
+    exactly_one: Vec<Vec<(usize, Option<usize>)>>,
+    hero_tree: Option<usize>,
+    weights: &'a Weights,
+    settings: &'a SolverSettings,
+}
+
+struct State {
+    ranks: Vec<usize>,
+    entries: Vec<Option<usize>>,
+    spent: Vec<usize>,
+    // spent in the variable's section before it was decided
+    spent_before: Vec<usize>,
+    score: f64,
+    steps: usize,
+    aborted: bool,
+}
+
+struct Found {
+    score: f64,
+    allocation: Vec<(usize, Option<usize>, usize)>,
+    hero_tree: Option<usize>,
+}
+
+impl Search<'_> {
+    fn upper_bound(&self, index: usize, state: &State) -> f64 {
+        let bound = (0..self.budgets.len())
+            .map(|section| {
+                let values = &self.remaining_values[index][section];
+                let points = self.budgets[section].saturating_sub(state.spent[section]);
+                values[points.min(values.len() - 1)]
+            })
+            .sum::<f64>();
+        state.score + bound
+    }
+
+    fn connected(&self, variable: &Variable, state: &State) -> bool {
+        variable.unconditional
+            || variable.parents.iter().any(|parent| {
+                let rank = state.ranks[*parent];
+                let max_ranks = self.variables[*parent]
+                    .options
+                    .iter()
+                    .find(|(entry_id, _)| *entry_id == state.entries[*parent])
+                    .map_or(1, |(_, max_ranks)| *max_ranks);
+                rank > 0 && rank >= max_ranks
+            })
+    }
+
+    fn exactly_one_violated(&self, index: usize, state: &State) -> bool {
+        self.exactly_one.iter().any(|group| {
+            let taken = group
+                .iter()
+                .filter(|(variable, entry_id)| {
+                    *variable < index
+                        && state.ranks[*variable] > 0
+                        && (entry_id.is_none() || state.entries[*variable] == *entry_id)
+                })
+                .count();
+            taken > 1 || (index == self.variables.len() && taken == 0)
+        })
+    }
+
+    fn record(&self, state: &State, found: &mut Vec<Found>) {
+        let allocation = self
+            .variables
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| state.ranks[*index] > 0)
+            .map(|(index, variable)| (variable.node_id, state.entries[index], state.ranks[index]))
+            .collect::<Vec<_>>();
+        let position = found
+            .iter()
+            .position(|build| build.score < state.score)
+            .unwrap_or(found.len());
+        found.insert(
+            position,
+            Found {
+                score: state.score,
+                allocation,
+                hero_tree: self.hero_tree,
+            },
+        );
+        found.truncate(self.settings.max_builds);
+    }
+
+    fn search(&self, index: usize, state: &mut State, found: &mut Vec<Found>) {
+        if state.aborted {
+            return;
+        }
+        state.steps += 1;
+        if state.steps > self.settings.max_steps {
+            state.aborted = true;
+            return;
+        }
+
+        let full = found.len() >= self.settings.max_builds;
+        if full
+            && found
+                .last()
+                .is_some_and(|worst| self.upper_bound(index, state) <= worst.score)
+        {
+            return;
+        }
+        // sections that have to be spent completely need enough ranks left
+        let unreachable = (0..self.budgets.len()).any(|section| {
+            self.spend_all[section]
+                && state.spent[section] + self.remaining_ranks[index][section]
+                    < self.budgets[section]
+        });
+        if unreachable || self.exactly_one_violated(index, state) {
+            return;
+        }
+
+        let Some(variable) = self.variables.get(index) else {
+            self.record(state, found);
+            return;
+        };
+
+        let section = variable.section;
+        state.spent_before[index] = state.spent[section];
+        let available = match self.connected(variable, state)
+            && state.spent_before[variable.row_start] >= variable.req_points
+        {
+            true => self.budgets[section].saturating_sub(state.spent[section]),
+            false => 0,
+        };
+
+        // higher ranks first, full builds are found before sparse ones
+        for rank in (0..=variable.max_rank.min(available)).rev() {
+            if rank < variable.min_rank {
+                break;
+            }
+            let options = match rank {
+                0 => &variable.options[..1],
+                _ => &variable.options[..],
+            };
+            for (entry_id, max_ranks) in options {
+                if rank > *max_ranks
+                    || (rank > 0 && variable.avoided_entries.contains(entry_id))
+                    || (rank > 0
+                        && variable.required_entry.is_some()
+                        && variable.required_entry != *entry_id)
+                {
+                    continue;
+                }
+                let value = self.weights.value(variable.node_id, *entry_id, rank);
+                state.ranks[index] = rank;
+                state.entries[index] = *entry_id;
+                state.spent[section] += rank;
+                state.score += value;
+
+                self.search(index + 1, state, found);
+
+                state.score -= value;
+                state.spent[section] -= rank;
+                state.ranks[index] = 0;
+                state.entries[index] = None;
+            }
+        }
+    }
+}
+
+impl TalentConfiguration {
+    fn search_sections(&self, hero_tree: Option<usize>) -> Vec<LayoutSection> {
+        let mut sections = vec![LayoutSection::Class, LayoutSection::Spec];
+        sections.extend(hero_tree.map(LayoutSection::Hero));
+        sections
+    }
+
+    fn build_search<'a>(
+        &self,
+        hero_tree: Option<usize>,
+        constraints: &[ResolvedConstraint],
+        weights: &'a Weights,
+        settings: &'a SolverSettings,
+    ) -> Search<'a> {
+        let sections = self.search_sections(hero_tree);
+        let mut nodes = self
+            .all_talents
+            .iter()
+            .filter(|te| {
+                let ttn = &te.trait_tree_node;
+                ttn.id != 0 && ttn.node_type != TraitTreeNodeType::SubTree
+            })
+            // granted nodes are always taken and cost nothing, they are not decisions
+            .filter(|te| !te.trait_tree_node.is_free())
+            .filter_map(|te| {
+                let section = self.layout_section(&te.trait_tree_node);
+                sections
+                    .iter()
+                    .position(|s| *s == section)
+                    .map(|section| (section, te))
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|(section, te)| {
+            let ttn = &te.trait_tree_node;
+            (*section, ttn.tier(), ttn.pos_x, ttn.id)
+        });
+
+        let free_nodes = self
+            .all_talents
+            .iter()
+            .map(|te| &te.trait_tree_node)
+            .filter(|ttn| ttn.is_free())
+            .map(|ttn| ttn.id)
+            .collect::<HashSet<_>>();
+        let index_of = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, (_, te))| (te.trait_tree_node.id, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut variables = Vec::with_capacity(nodes.len());
+        for (index, (section, te)) in nodes.iter().enumerate() {
+            let ttn = &te.trait_tree_node;
+            let options = match ttn.node_type {
+                TraitTreeNodeType::Choice => ttn
+                    .entries
+                    .iter()
+                    .map(|tte| (tte.id, tte.max_ranks.or(ttn.max_ranks).unwrap_or(1)))
+                    .collect::<Vec<_>>(),
+                _ => vec![(te.trait_tree_entry.id, te.max_ranks())],
+            };
+            let row_start = match variables.last() {
+                Some(Variable { row_start, .. })
+                    if nodes[index - 1].0 == *section
+                        && nodes[index - 1].1.trait_tree_node.tier() == ttn.tier() =>
+                {
+                    *row_start
+                }
+                _ => index,
+            };
+
+            let mut variable = Variable {
+                node_id: ttn.id,
+                section: *section,
+                max_rank: options.iter().map(|(_, max)| *max).max().unwrap_or(1),
+                options,
+                parents: ttn
+                    .prev
+                    .iter()
+                    .filter_map(|id| index_of.get(id).copied())
+                    .collect(),
+                unconditional: ttn.entry_node.unwrap_or(false)
+                    || ttn.prev.is_empty()
+                    || ttn.prev.iter().any(|id| free_nodes.contains(id)),
+                req_points: ttn.req_points.unwrap_or(0),
+                row_start,
+                min_rank: 0,
+                required_entry: None,
+                avoided_entries: Vec::new(),
+            };
+            for constraint in constraints {
+                match constraint {
+                    ResolvedConstraint::Require { target, ranks } if target.node_id == ttn.id => {
+                        variable.min_rank = variable.min_rank.max(*ranks);
+                        variable.required_entry = target.entry_id.or(variable.required_entry);
+                    }
+                    ResolvedConstraint::Avoid { target } if target.node_id == ttn.id => {
+                        match target.entry_id {
+                            Some(_) => variable.avoided_entries.push(target.entry_id),
+                            None => variable.max_rank = 0,
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            variables.push(variable);
+        }
+
+        let budgets = sections
+            .iter()
+            .map(|section| self.point_budget(*section))
+            .collect::<Vec<_>>();
+        let spend_all = sections
+            .iter()
+            .map(|layout_section| {
+                constraints.iter().any(|constraint| match constraint {
+                    ResolvedConstraint::SpendAll { section } => section.covers(*layout_section),
+                    _ => false,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // suffix tables, index `variables.len()` is the empty suffix
+        let mut remaining_ranks = vec![vec![0; sections.len()]; variables.len() + 1];
+        let mut remaining_values = vec![vec![vec![0.0]; sections.len()]; variables.len() + 1];
+        let mut marginals: Vec<Vec<f64>> = vec![Vec::new(); sections.len()];
+        for index in (0..variables.len()).rev() {
+            let variable = &variables[index];
+            remaining_ranks[index] = remaining_ranks[index + 1].clone();
+            remaining_ranks[index][variable.section] += variable.max_rank;
+
+            for rank in 1..=variable.max_rank {
+                let best = variable
+                    .options
+                    .iter()
+                    .map(|(entry_id, _)| weights.marginal(variable.node_id, *entry_id, rank))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                if best > 0.0 {
+                    marginals[variable.section].push(best);
+                }
+            }
+            remaining_values[index] = marginals
+                .iter()
+                .enumerate()
+                .map(|(section, values)| {
+                    let mut values = values.clone();
+                    values.sort_by(|a, b| b.total_cmp(a));
+                    values.truncate(budgets[section]);
+                    let mut sums = vec![0.0];
+                    for value in values {
+                        sums.push(sums.last().copied().unwrap_or(0.0) + value);
+                    }
+                    sums
+                })
+                .collect();
+        }
+
+        // targets outside of this search, e.g. in another hero tree, can never be taken
+        let exactly_one = constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                ResolvedConstraint::ExactlyOne { targets } => Some(
+                    targets
+                        .iter()
+                        .filter_map(|target| {
+                            index_of
+                                .get(&target.node_id)
+                                .map(|index| (*index, target.entry_id))
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect();
+
+        Search {
+            variables,
+            budgets,
+            spend_all,
+            remaining_ranks,
+            remaining_values,
+            exactly_one,
+            hero_tree,
+            weights,
+            settings,
+        }
+    }
+
+    // highest scoring legal builds, with empty weights simply the first ones found
+    pub fn solve(
+        &self,
+        constraints: &[ResolvedConstraint],
+        weights: &Weights,
+        settings: &SolverSettings,
+        config: &TalentEncoding,
+    ) -> SolverOutcome {
+        let hero_trees = match self
+            .hero_trees()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>()
+        {
+            hero_trees if hero_trees.is_empty() => vec![None],
+            hero_trees => hero_trees.into_iter().map(Some).collect(),
+        };
+        // a required hero talent rules out every other hero tree, their searches would only
+        // fill `found` with builds that get filtered out below
+        let required_hero_trees = constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                ResolvedConstraint::HeroTree { trait_sub_tree_id } => Some(*trait_sub_tree_id),
+                ResolvedConstraint::Require { target, ranks } if *ranks > 0 => self
+                    .talent(target.node_id)
+                    .and_then(|te| te.trait_tree_node.trait_sub_tree_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // every hero tree search is bounded by the builds found in the ones before it,
+        // unless each hero tree keeps its own best builds
+        let mut found: Vec<Found> = Vec::new();
+        let mut finished: Vec<Found> = Vec::new();
+        let mut steps = 0;
+        let mut complete = true;
+        for hero_tree in hero_trees {
+            if !required_hero_trees
+                .iter()
+                .all(|required| hero_tree == Some(*required))
+            {
+                continue;
+            }
+            let search = self.build_search(hero_tree, constraints, weights, settings);
+            let mut state = State {
+                ranks: vec![0; search.variables.len()],
+                entries: vec![None; search.variables.len()],
+                spent: vec![0; search.budgets.len()],
+                spent_before: vec![0; search.variables.len()],
+                score: 0.0,
+                steps: 0,
+                aborted: false,
+            };
+            search.search(0, &mut state, &mut found);
+            steps += state.steps;
+            complete &= !state.aborted;
+            if settings.per_hero_tree {
+                finished.append(&mut found);
+            }
+        }
+        finished.append(&mut found);
+
+        SolverOutcome {
+            builds: finished
+                .into_iter()
+                .map(|build| SolvedBuild {
+                    configuration: self.with_allocation(&build.allocation, build.hero_tree, config),
+                    score: build.score,
+                    hero_tree: build.hero_tree,
+                })
+                // guards against the search model drifting from the constraint checks
+                .filter(|build| build.configuration.satisfies_all(constraints))
+                .collect(),
+            complete,
+            steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::ConstraintSet;
+    use crate::test_tree::empty_configuration;
+
+    fn settings(max_builds: usize) -> SolverSettings {
+        SolverSettings {
+            max_builds,
+            per_hero_tree: true,
+            ..Default::default()
+        }
+    }
+
+    fn weights(values: &[(usize, Option<usize>, f64)]) -> Weights {
+        let mut weights = Weights::default();
+        for (node_id, entry_id, value) in values {
+            let target = NodeTarget {
+                node_id: *node_id,
+                entry_id: *entry_id,
+            };
+            weights.set(target, vec![*value]);
+        }
+        weights
+    }
+
+    fn rank(build: &SolvedBuild, node_id: usize) -> usize {
+        build
+            .configuration
+            .talent(node_id)
+            .map_or(0, |talent| talent.rank)
+    }
+
+    fn solve(constraints: &str, weights: &Weights, max_builds: usize) -> SolverOutcome {
+        let configuration = empty_configuration();
+        let constraints = constraints
+            .parse::<ConstraintSet>()
+            .unwrap()
+            .resolve(&configuration)
+            .unwrap();
+        configuration.solve(
+            &constraints,
+            weights,
+            &settings(max_builds),
+            &TalentEncoding::default(),
+        )
+    }
+
+    #[test]
+    fn budgets_are_respected() {
+        let weights = weights(&[
+            (1, None, 1.0),
+            (2, None, 1.0),
+            (5, None, 1.0),
+            (7, None, 1.0),
+        ]);
+        let outcome = solve("", &weights, 20);
+        assert!(outcome.complete);
+        assert!(!outcome.builds.is_empty());
+        for build in &outcome.builds {
+            let configuration = &build.configuration;
+            assert!(configuration.is_legal(), "{}", configuration.string);
+            for section in configuration.search_sections(build.hero_tree) {
+                assert!(configuration.points_spent(section) <= configuration.point_budget(section));
+            }
+        }
+    }
+
+    #[test]
+    fn gated_rows_need_the_points_above() {
+        let outcome = solve("", &weights(&[(4, None, 10.0)]), 5);
+        for build in &outcome.builds {
+            assert!(
+                build.configuration.is_legal(),
+                "{}",
+                build.configuration.string
+            );
+        }
+        // Deep Focus needs 3 points above it, Steady Aim and both ranks of Quick Shot
+        let best = &outcome.builds[0];
+        assert_eq!(best.score, 10.0);
+        assert_eq!(
+            [1, 2, 4].map(|node_id| rank(best, node_id)),
+            [1, 2, 1],
+            "{}",
+            best.configuration.string
+        );
+    }
+
+    #[test]
+    fn entry_ranks_come_before_node_ranks() {
+        // Long Reach only has ranks on its entry
+        let outcome = solve("", &weights(&[(5, None, 1.0)]), 1);
+        assert_eq!(rank(&outcome.builds[0], 5), 2);
+        assert_eq!(outcome.builds[0].score, 2.0);
+    }
+
+    #[test]
+    fn required_and_avoided_talents_hold() {
+        let constraints =
+            "require \"Long Reach\" at 2 ranks; avoid \"Right Path\"; avoid \"Quick Shot\"";
+        let weights = weights(&[(2, None, 5.0), (3, Some(31), 5.0), (3, Some(30), 1.0)]);
+        let outcome = solve(constraints, &weights, 10);
+        assert!(!outcome.builds.is_empty());
+        for build in &outcome.builds {
+            assert_eq!(rank(build, 5), 2);
+            assert_eq!(rank(build, 2), 0);
+            let split_path = build.configuration.talent(3).unwrap();
+            assert!(split_path.rank == 0 || split_path.trait_tree_entry.id == Some(30));
+        }
+        assert_eq!(outcome.builds[0].score, 1.0);
+    }
+
+    #[test]
+    fn each_build_has_one_hero_tree() {
+        let weights = weights(&[
+            (10, None, 1.0),
+            (11, None, 2.0),
+            (12, None, 1.0),
+            (13, None, 2.0),
+        ]);
+        let outcome = solve("", &weights, 2);
+        let hero_trees = outcome
+            .builds
+            .iter()
+            .map(|build| build.hero_tree)
+            .collect::<HashSet<_>>();
+        assert_eq!(hero_trees, HashSet::from([Some(50), Some(51)]));
+        for build in &outcome.builds {
+            let hero_tree = build.hero_tree.unwrap();
+            assert_eq!(build.configuration.subtrees, vec![hero_tree]);
+            assert!(build
+                .configuration
+                .selected_talents
+                .iter()
+                .all(|talent| talent
+                    .trait_tree_node
+                    .trait_sub_tree_id
+                    .is_none_or(|id| id == hero_tree)));
+        }
+    }
+
+    #[test]
+    fn required_hero_talent_picks_its_hero_tree() {
+        let outcome = solve("require \"Ranger Volley\"", &Weights::default(), 5);
+        assert!(!outcome.builds.is_empty());
+        for build in &outcome.builds {
+            assert_eq!(build.hero_tree, Some(50));
+            assert_eq!([10, 12].map(|node_id| rank(build, node_id)), [1, 1]);
+        }
+    }
+}
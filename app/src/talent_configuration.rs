@@ -1,10 +1,21 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use leptos::ev::{KeyboardEvent, PointerEvent, WheelEvent};
+use leptos::wasm_bindgen::JsCast;
 use leptos::{either::Either, leptos_dom::logging::console_log, prelude::*};
 use thaw::{Tooltip, TooltipAppearance};
 use thiserror::Error;
 
+use crate::export::{export_name, export_tree, ExportError, ExportFormat};
+use crate::icons::{use_icon_data, IconManifest};
+use crate::layout::{LayoutSection, LayoutSettings, TreeLayout, Viewport};
+use crate::search::TalentSearch;
 use crate::talent_encoding::{TalentEncoding, TalentEncodingError};
+use crate::talent_path::TalentPathView;
+use crate::theme::TreeTheme;
 use crate::trait_tree::{
-    fetch_trait_trees, TraitTree, TraitTreeEntry, TraitTreeEntryType, TraitTreeNode,
+    use_trait_tree_data, TraitTree, TraitTreeEntry, TraitTreeEntryType, TraitTreeNode,
     TraitTreeNodeType,
 };
 
@@ -14,6 +25,15 @@ pub enum TalentConfigurationError {
     TalentEncodingError(#[from] TalentEncodingError),
     #[error("Specialization not found in data.")]
     SpecNotFound,
+    #[error("Node {0} has no entries in data.")]
+    NodeWithoutEntries(usize),
+    #[error("Choice {choice} does not exist on node {node_id}.")]
+    ChoiceOutOfRange { node_id: usize, choice: usize },
+    #[error("Hero tree option {entry_id:?} on node {node_id} has no hero tree in data.")]
+    SubTreeWithoutId {
+        node_id: usize,
+        entry_id: Option<usize>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -23,14 +43,131 @@ pub struct TalentEntry {
     pub trait_tree_entry: TraitTreeEntry,
 }
 
+impl TalentEntry {
+    pub fn max_ranks(&self) -> usize {
+        self.trait_tree_entry
+            .max_ranks
+            .or(self.trait_tree_node.max_ranks)
+            .unwrap_or(1)
+    }
+
+    pub fn is_maxed(&self) -> bool {
+        self.rank > 0 && self.rank >= self.max_ranks()
+    }
+
+    // unchosen choice nodes describe every option, everything else the current entry
+    pub fn tooltip(&self) -> String {
+        let entries = match (&self.trait_tree_node.node_type, self.rank) {
+            (TraitTreeNodeType::Choice, 0) => self.trait_tree_node.entries.clone(),
+            _ => vec![self.trait_tree_entry.clone()],
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                let name = entry
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| self.trait_tree_node.name.clone());
+                let max_ranks = entry
+                    .max_ranks
+                    .or(self.trait_tree_node.max_ranks)
+                    .unwrap_or(1);
+                let mut lines = vec![name, format!("Rank {}/{}", self.rank, max_ranks)];
+                let details = entry.spell_details();
+                if !details.is_empty() {
+                    lines.push(details.join(", "));
+                }
+                if let Some(description) = entry.description_at_rank(self.rank) {
+                    lines.push(description);
+                }
+                if self.rank > 0 && self.rank < max_ranks {
+                    if let Some(next) = entry.description_at_rank(self.rank + 1) {
+                        lines.push(format!("Next rank: {next}"));
+                    }
+                }
+                lines.join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gate {
+    pub section: LayoutSection,
+    pub req_points: usize,
+    // the gate sits above this tier
+    pub tier: usize,
+    // where that row is drawn, see `LayoutSettings::row_y`
+    pub node_tier: Option<usize>,
+    pub pos_y: i32,
+    pub spent: usize,
+}
+
+impl Gate {
+    fn new(
+        talent_configuration: &TalentConfiguration,
+        node: &TraitTreeNode,
+        section: LayoutSection,
+        req_points: usize,
+    ) -> Self {
+        Self {
+            section,
+            req_points,
+            tier: node.tier(),
+            node_tier: node.node_tier,
+            pos_y: node.pos_y,
+            spent: talent_configuration.points_spent_before(node),
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.spent >= self.req_points
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeState {
+    // both ends are taken
+    Taken,
+    // the source is maxed, the target can be taken
+    Available,
+    // the source is not maxed yet
+    Blocked,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeroTreeDisplay {
+    #[default]
+    Selected,
+    Both,
+}
+
+// changes a single node can receive from the tree view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeAction {
+    Allocate,
+    Deallocate,
+    CycleChoice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    Allocated,
+    Available,
+    Locked,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TalentConfiguration {
     pub string: String,
     pub spec: usize,
-    // TODO: use hash_map instead of vec?
     pub selected_talents: Vec<TalentEntry>,
     pub unselected_talents: Vec<TalentEntry>,
     pub all_talents: Vec<TalentEntry>,
+    // node id to position in `all_talents`, which keeps its order once built
+    pub talent_index: HashMap<usize, usize>,
     pub subtrees: Vec<usize>,
     pub trait_tree: TraitTree,
 }
@@ -41,6 +178,8 @@ impl TalentConfiguration {
         config: TalentEncoding,
         trait_tree_data: Vec<TraitTree>,
     ) -> Result<Self, TalentConfigurationError> {
+        config.valid_base64(s)?;
+
         let mut bit_head: usize = 0;
         let mut iter = s.chars().peekable();
 
@@ -50,7 +189,7 @@ impl TalentConfiguration {
                 let Some(char) = iter.peek() else {
                     return value;
                 };
-                // already validated that char in config.base64_chars in config.is_valid, panic is fine
+                // already validated that char in config.base64_chars by config.valid_base64 above, panic is fine
                 let char_position = config.find_char_unchecked(&char.to_string());
                 // use checked_shr to allow to shift into zero without panic
                 let bit_index_set: usize = char_position
@@ -72,21 +211,18 @@ impl TalentConfiguration {
 
         let serialization_version = get_bits(config.version_bits);
         let spec = get_bits(config.spec_bits);
-        let _ = get_bits(config.tree_bits);
+        let tree_hash = (0..config.tree_bits)
+            .step_by(8)
+            .map(|offset| get_bits(std::cmp::min(8, config.tree_bits - offset)) as u8)
+            .collect::<Vec<_>>();
 
-        let Some(trait_tree) = trait_tree_data.into_iter().find(|tt| tt.spec_id == spec) else {
+        let Some(mut trait_tree) = trait_tree_data.into_iter().find(|tt| tt.spec_id == spec) else {
             return Err(TalentConfigurationError::SpecNotFound);
         };
-
-        let mut talent_entries = trait_tree
-            .class_nodes
-            .clone()
-            .into_iter()
-            .chain(trait_tree.spec_nodes.clone().into_iter())
-            .chain(trait_tree.hero_nodes.clone().into_iter())
-            .chain(trait_tree.sub_tree_nodes.clone().into_iter())
-            .collect::<Vec<_>>();
-        talent_entries.sort_by(|a, b| (&a.id).cmp(&b.id));
+        // trees that did not come from fetch_trait_trees
+        if trait_tree.index.is_empty() {
+            trait_tree.build_index();
+        }
 
         // TODO: encode number of allotted TTN in data
         let mut selected_talents: Vec<TalentEntry> = Vec::with_capacity(80);
@@ -98,7 +234,7 @@ impl TalentConfiguration {
         // and get rid of all of this unwrap
         for entry in trait_tree.full_node_order.iter() {
             let mut skip: bool = false;
-            let selected_node = match talent_entries.iter().find(|ttn| ttn.id == *entry) {
+            let selected_node = match trait_tree.node(*entry) {
                 Some(node) => node.clone(),
                 None => {
                     skip = true;
@@ -107,7 +243,9 @@ impl TalentConfiguration {
             };
             let mut selected_trait = match skip {
                 true => TraitTreeEntry::default(),
-                false => selected_node.entries.first().unwrap().clone(),
+                false => selected_node.entries.first().cloned().ok_or(
+                    TalentConfigurationError::NodeWithoutEntries(selected_node.id),
+                )?,
             };
             let mut rank: usize = 0;
 
@@ -131,13 +269,21 @@ impl TalentConfiguration {
                     if get_bits(1) == 1 {
                         // choice
                         let choice_bits = get_bits(config.choice_bits);
-                        selected_trait = selected_node.entries[choice_bits].clone();
+                        selected_trait = selected_node.entries.get(choice_bits).cloned().ok_or(
+                            TalentConfigurationError::ChoiceOutOfRange {
+                                node_id: selected_node.id,
+                                choice: choice_bits,
+                            },
+                        )?;
 
                         match selected_trait.node_type {
                             Some(TraitTreeEntryType::SubTree) => {
-                                subtrees.push(selected_trait.trait_sub_tree_id.expect(
-                                    "A SubTree selection node does not have a trait_tree_id!",
-                                ));
+                                subtrees.push(selected_trait.trait_sub_tree_id.ok_or(
+                                    TalentConfigurationError::SubTreeWithoutId {
+                                        node_id: selected_node.id,
+                                        entry_id: selected_trait.id,
+                                    },
+                                )?);
                             }
                             _ => (),
                         };
@@ -150,6 +296,14 @@ impl TalentConfiguration {
                         rank,
                     });
                 }
+            } else if selected_node.is_free() {
+                // granted nodes are taken even when the string leaves them out
+                rank = selected_node.max_ranks.unwrap_or(1);
+                selected_talents.push(TalentEntry {
+                    trait_tree_node: selected_node.clone(),
+                    trait_tree_entry: selected_trait.clone(),
+                    rank,
+                });
             } else {
                 if !skip {
                     unselected_talents.push(TalentEntry {
@@ -166,29 +320,125 @@ impl TalentConfiguration {
             });
         }
 
-        match config.is_valid(s, serialization_version) {
-            Ok(()) => Ok(Self {
-                string: s.to_string(),
-                spec,
-                selected_talents,
-                unselected_talents,
-                all_talents,
-                subtrees,
-                trait_tree,
-            }),
-            Err(err) => Err(TalentConfigurationError::TalentEncodingError(err)),
+        config.is_valid(s, serialization_version)?;
+        // the shipped talent data has no `treeHash` yet, so this check stays off until it does,
+        // any hash in a string is accepted without one
+        if let Some(expected) = &trait_tree.tree_hash {
+            config.valid_tree_hash(&tree_hash, expected)?;
         }
+
+        Ok(Self {
+            string: s.to_string(),
+            spec,
+            selected_talents,
+            unselected_talents,
+            talent_index: index_talents(&all_talents),
+            all_talents,
+            subtrees,
+            trait_tree,
+        })
+    }
+
+    pub fn encode(&self, config: &TalentEncoding) -> String {
+        let mut bits: Vec<usize> =
+            Vec::with_capacity(config.version_bits + config.spec_bits + config.tree_bits + 160);
+
+        let mut put_bits = |value: usize, count: usize| {
+            for offset in 0..count {
+                // use checked_shr to allow to shift into zero without panic
+                bits.push(value.checked_shr(offset.try_into().unwrap()).unwrap_or(0) & 0b1);
+            }
+        };
+
+        put_bits(config.serialization_version, config.version_bits);
+        put_bits(self.spec, config.spec_bits);
+        // zeros when the hash is unknown, the game accepts those
+        let tree_hash = self.trait_tree.tree_hash.as_deref().unwrap_or_default();
+        for offset in (0..config.tree_bits).step_by(8) {
+            let byte = tree_hash.get(offset / 8).copied().unwrap_or(0);
+            put_bits(byte as usize, std::cmp::min(8, config.tree_bits - offset));
+        }
+
+        for id in self.trait_tree.full_node_order.iter() {
+            let Some(entry) = self.talent(*id).filter(|te| te.rank > 0) else {
+                put_bits(0, 1);
+                continue;
+            };
+            let node = &entry.trait_tree_node;
+
+            // granted nodes are selected but not purchased, choice nodes too, so their option is
+            // not written and reads back as the first one
+            if node.is_free() {
+                put_bits(1, 1);
+                put_bits(0, 1);
+                continue;
+            }
+
+            // selected, purchased
+            put_bits(1, 1);
+            put_bits(1, 1);
+
+            match entry.rank < node.max_ranks.unwrap_or(1) {
+                true => {
+                    put_bits(1, 1);
+                    put_bits(entry.rank, config.rank_bits);
+                }
+                false => put_bits(0, 1),
+            }
+
+            match node.node_type {
+                TraitTreeNodeType::Single => put_bits(0, 1),
+                TraitTreeNodeType::Choice | TraitTreeNodeType::SubTree => {
+                    let choice = node
+                        .entries
+                        .iter()
+                        .position(|tte| tte.id == entry.trait_tree_entry.id)
+                        .unwrap_or(0);
+                    put_bits(1, 1);
+                    put_bits(choice, config.choice_bits);
+                }
+            }
+        }
+
+        bits.chunks(config.byte_size)
+            .map(|chunk| {
+                let char_position = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (offset, bit)| acc | (bit << offset));
+                config.base64_chars.chars().nth(char_position).unwrap()
+            })
+            .collect()
     }
 
+    // the spec a string was exported for, read from its header alone
+    pub fn spec_from_str(
+        s: &str,
+        config: &TalentEncoding,
+    ) -> Result<usize, TalentConfigurationError> {
+        config.valid_base64(s)?;
+        let bits = s.chars().flat_map(|char| {
+            let char_position = config.find_char_unchecked(&char.to_string());
+            (0..config.byte_size).map(move |offset| (char_position >> offset) & 0b1)
+        });
+        Ok(bits
+            .skip(config.version_bits)
+            .take(config.spec_bits)
+            .enumerate()
+            .fold(0, |spec, (offset, bit)| spec | (bit << offset)))
+    }
+
+    // nothing allocated but the granted nodes
     pub fn new(
+        spec: usize,
         config: TalentEncoding,
         trait_tree_data: Vec<TraitTree>,
     ) -> Result<Self, TalentConfigurationError> {
         let Some(trait_tree) = trait_tree_data
             .iter()
-            .find(|trait_tree| trait_tree.spec_id == 268)
+            .find(|trait_tree| trait_tree.spec_id == spec)
         else {
-            panic!()
+            return Err(TalentConfigurationError::SpecNotFound);
         };
         let unselected_talents = trait_tree
             .class_nodes
@@ -198,118 +448,340 @@ impl TalentConfiguration {
             .chain(trait_tree.sub_tree_nodes.clone().iter())
             .map(|node| TalentEntry {
                 trait_tree_node: node.clone(),
-                rank: 0,
-                trait_tree_entry: Default::default(),
+                rank: match node.is_free() {
+                    true => node.max_ranks.unwrap_or(1),
+                    false => 0,
+                },
+                // same as decoding, unselected nodes show their first entry
+                trait_tree_entry: node.entries.first().cloned().unwrap_or_default(),
             })
             .collect::<Vec<_>>();
 
-        Ok(Self {
+        let mut talent_configuration = Self {
             string: "".to_string(),
             spec: trait_tree.spec_id,
+            talent_index: index_talents(&unselected_talents),
             all_talents: unselected_talents.clone(),
             unselected_talents,
             selected_talents: Default::default(),
             subtrees: Default::default(),
             trait_tree: trait_tree.clone(),
-        })
+        };
+        talent_configuration.refresh_selection();
+        talent_configuration.string = talent_configuration.encode(&config);
+
+        Ok(talent_configuration)
     }
 
-    fn compute_hero_talent_normalization(&self) -> (i32, i32) {
-        // TODO: bake this into data
-        let class_x_max = self
+    pub fn hero_trees(&self) -> Vec<(usize, String)> {
+        let mut hero_trees = self
             .trait_tree
-            .class_nodes
+            .sub_tree_nodes
             .iter()
-            .map(|entry| entry.pos_x)
-            .max()
-            .unwrap_or(0);
+            .flat_map(|node| node.entries.iter())
+            .filter_map(|entry| {
+                entry
+                    .trait_sub_tree_id
+                    .map(|id| (id, entry.name.clone().unwrap_or_default()))
+            })
+            .collect::<Vec<_>>();
 
-        let spec_x_min = self
-            .trait_tree
-            .spec_nodes
-            .iter()
-            .map(|entry| entry.pos_x)
-            .min()
-            .unwrap_or(100);
+        // fall back to the hero nodes themselves if the data has no selection node
+        if hero_trees.is_empty() {
+            hero_trees = self
+                .trait_tree
+                .hero_nodes
+                .iter()
+                .filter_map(|node| node.trait_sub_tree_id)
+                .map(|id| (id, "".to_string()))
+                .collect();
+        }
+        hero_trees.sort();
+        hero_trees.dedup_by_key(|(id, _)| *id);
+        hero_trees
+    }
 
-        let class_y_min = self
-            .trait_tree
-            .class_nodes
-            .iter()
-            .map(|entry| entry.pos_y)
-            .min()
-            .unwrap_or(0);
+    pub fn visible_hero_trees(&self, hero_tree_display: HeroTreeDisplay) -> Vec<usize> {
+        let selected = self
+            .hero_trees()
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| self.subtrees.contains(id))
+            .collect::<Vec<_>>();
 
-        let class_y_max = self
-            .trait_tree
-            .class_nodes
-            .iter()
-            .map(|entry| entry.pos_y)
-            .max()
-            .unwrap_or(100);
+        match (hero_tree_display, selected.is_empty()) {
+            (HeroTreeDisplay::Selected, false) => selected,
+            _ => self.hero_trees().into_iter().map(|(id, _)| id).collect(),
+        }
+    }
 
-        let hero_y_min = self
-            .trait_tree
-            .hero_nodes
-            .iter()
-            .map(|entry| entry.pos_y)
-            .min()
-            .unwrap_or(0);
+    pub fn select_hero_tree(&mut self, trait_sub_tree_id: usize, config: &TalentEncoding) {
+        for talent in self.all_talents.iter_mut() {
+            let node = &talent.trait_tree_node;
+            if node.node_type == TraitTreeNodeType::SubTree {
+                if let Some(entry) = node
+                    .entries
+                    .iter()
+                    .find(|entry| entry.trait_sub_tree_id == Some(trait_sub_tree_id))
+                {
+                    talent.rank = node.max_ranks.unwrap_or(1);
+                    talent.trait_tree_entry = entry.clone();
+                }
+            } else if node
+                .trait_sub_tree_id
+                .is_some_and(|id| id != trait_sub_tree_id)
+            {
+                // points in the hero tree being switched away from are refunded
+                talent.rank = 0;
+            }
+        }
 
-        let hero_y_max = self
-            .trait_tree
-            .hero_nodes
-            .iter()
-            .map(|entry| entry.pos_y)
-            .max()
-            .unwrap_or(100);
+        self.subtrees = vec![trait_sub_tree_id];
+        self.refresh_selection();
+        self.string = self.encode(config);
+    }
 
-        let entry = self
-            .trait_tree
-            .hero_nodes
+    // copy with exactly the given `(node id, entry id, rank)` allocations, everything else refunded
+    pub fn with_allocation(
+        &self,
+        allocation: &[(usize, Option<usize>, usize)],
+        hero_tree: Option<usize>,
+        config: &TalentEncoding,
+    ) -> Self {
+        let mut configuration = self.clone();
+        if let Some(trait_sub_tree_id) = hero_tree {
+            configuration.select_hero_tree(trait_sub_tree_id, config);
+        }
+
+        for talent in configuration.all_talents.iter_mut() {
+            let node = &talent.trait_tree_node;
+            if node.node_type == TraitTreeNodeType::SubTree || node.is_free() {
+                continue;
+            }
+            match allocation
+                .iter()
+                .find(|(node_id, _, _)| *node_id == node.id)
+            {
+                Some((_, entry_id, rank)) => {
+                    if let Some(entry) = node.entries.iter().find(|tte| tte.id == *entry_id) {
+                        talent.trait_tree_entry = entry.clone();
+                    }
+                    talent.rank = *rank;
+                }
+                None => talent.rank = 0,
+            }
+        }
+
+        configuration.refresh_selection();
+        configuration.string = configuration.encode(config);
+        configuration
+    }
+
+    pub fn refresh_selection(&mut self) {
+        let (selected, unselected): (Vec<_>, Vec<_>) = self
+            .all_talents
             .iter()
-            .find(|entry| match entry.entry_node {
-                Some(true) => self
-                    .subtrees
-                    .contains(&entry.trait_sub_tree_id.unwrap_or(0)),
-                _ => false,
+            .filter(|talent| {
+                talent.trait_tree_node.id != 0
+                    && talent.trait_tree_node.node_type != TraitTreeNodeType::SubTree
             })
-            .unwrap();
+            .cloned()
+            .partition(|talent| talent.rank > 0);
+        self.selected_talents = selected;
+        self.unselected_talents = unselected;
+    }
 
-        let (root_x, root_y) = (entry.pos_x, entry.pos_y);
+    pub fn talent(&self, node_id: usize) -> Option<&TalentEntry> {
+        self.all_talents.get(*self.talent_index.get(&node_id)?)
+    }
 
-        let (rv_x, rv_y) = (
-            (class_x_max + spec_x_min) / 2 - root_x,
-            (class_y_min + class_y_max) / 2 - (hero_y_max - hero_y_min) / 4 - root_y,
-        );
+    // reachable from an entry node through a maxed parent, hero nodes only in the selected hero tree
+    pub fn prerequisites_met(&self, node: &TraitTreeNode) -> bool {
+        let hero_tree_selected = node
+            .trait_sub_tree_id
+            .is_none_or(|id| self.subtrees.contains(&id));
+        let connected = node.entry_node.unwrap_or(false)
+            || node.prev.is_empty()
+            || node
+                .prev
+                .iter()
+                .any(|id| self.talent(*id).is_some_and(TalentEntry::is_maxed));
+
+        hero_tree_selected && connected
+    }
+
+    pub fn can_allocate(&self, talent: &TalentEntry) -> bool {
+        let node = &talent.trait_tree_node;
+        let section = self.layout_section(node);
+
+        talent.rank < talent.max_ranks()
+            && self.prerequisites_met(node)
+            && self.gate_unlocked(node)
+            && self.points_spent(section) < self.point_budget(section)
+    }
+
+    pub fn node_state(&self, talent: &TalentEntry) -> NodeState {
+        match (talent.rank > 0, self.can_allocate(talent)) {
+            (true, _) => NodeState::Allocated,
+            (false, true) => NodeState::Available,
+            (false, false) => NodeState::Locked,
+        }
+    }
+
+    fn node_color(&self, talent: &TalentEntry, theme: &TreeTheme) -> String {
+        let node = &talent.trait_tree_node;
+        let color = match (self.node_state(talent), &node.node_type) {
+            _ if node.is_free() => &theme.granted,
+            (NodeState::Allocated, _) if !talent.is_maxed() => &theme.partial,
+            (NodeState::Allocated, _) => &theme.selected,
+            (NodeState::Locked, _) => &theme.locked,
+            (NodeState::Available, TraitTreeNodeType::Choice) => &theme.choice,
+            (NodeState::Available, _) if node.trait_sub_tree_id.is_some() => &theme.hero,
+            (NodeState::Available, _) => &theme.unselected,
+        };
+        color.clone()
+    }
+
+    // allocated nodes that lost their connection or gate, imported strings may already have some
+    fn stranded_talents(&self) -> usize {
+        self.selected_talents
+            .iter()
+            .filter(|te| !te.trait_tree_node.is_free())
+            .filter(|te| {
+                !self.prerequisites_met(&te.trait_tree_node)
+                    || !self.gate_unlocked(&te.trait_tree_node)
+            })
+            .count()
+    }
 
-        (rv_x, rv_y)
+    // nothing stranded, no rank above its maximum and no section over budget
+    pub fn is_legal(&self) -> bool {
+        self.stranded_talents() == 0
+            && self.selected_talents.iter().all(|te| {
+                let section = self.layout_section(&te.trait_tree_node);
+                te.rank <= te.max_ranks()
+                    && self.points_spent(section) <= self.point_budget(section)
+            })
     }
 
-    fn scale(&self, x: i32, y: i32) -> (i32, i32) {
-        // TODO: bake this into data
-        const SCALE_FACTOR: i32 = 15;
+    // returns whether anything changed, refunds that would strand other nodes are refused
+    pub fn apply(&mut self, node_id: usize, action: NodeAction, config: &TalentEncoding) -> bool {
+        let Some(&index) = self.talent_index.get(&node_id) else {
+            return false;
+        };
+        let talent = &self.all_talents[index];
+
+        match action {
+            NodeAction::Allocate => {
+                if !self.can_allocate(talent) {
+                    return false;
+                }
+                self.all_talents[index].rank += 1;
+                self.refresh_selection();
+            }
+            NodeAction::Deallocate => {
+                if talent.rank == 0 || talent.trait_tree_node.is_free() {
+                    return false;
+                }
+                let stranded = self.stranded_talents();
+                self.all_talents[index].rank -= 1;
+                self.refresh_selection();
+                if self.stranded_talents() > stranded {
+                    self.all_talents[index].rank += 1;
+                    self.refresh_selection();
+                    return false;
+                }
+            }
+            NodeAction::CycleChoice => {
+                let node = &talent.trait_tree_node;
+                if node.node_type != TraitTreeNodeType::Choice || node.entries.len() < 2 {
+                    return false;
+                }
+                let next = node
+                    .entries
+                    .iter()
+                    .position(|tte| tte.id == talent.trait_tree_entry.id)
+                    .map_or(0, |position| (position + 1) % node.entries.len());
+                let entry = node.entries[next].clone();
+                let talent = &mut self.all_talents[index];
+                talent.trait_tree_entry = entry;
+                talent.rank = talent.rank.min(talent.max_ranks());
+                self.refresh_selection();
+            }
+        }
 
-        (x / SCALE_FACTOR, y / SCALE_FACTOR)
+        self.string = self.encode(config);
+        true
     }
 
-    fn coordinate_transformation(&self, entry: &TalentEntry) -> (i32, i32) {
-        // TODO: bake this into data
-        let (x_offset, y_offset) = match entry.trait_tree_node.trait_sub_tree_id {
-            Some(_) => self.compute_hero_talent_normalization(),
-            None => (0, 0),
+    // closest drawn node in each arrow key direction: up and down follow edges, left and right stay on the row
+    fn neighbours(&self, talent: &TalentEntry, ctx: &DrawContext) -> Neighbours {
+        let node = &talent.trait_tree_node;
+        let section = self.layout_section(node);
+        let closest = |candidates: Vec<&TalentEntry>| {
+            candidates
+                .into_iter()
+                .filter(|te| self.do_draw(te, ctx))
+                .min_by_key(|te| (te.trait_tree_node.pos_x - node.pos_x).abs())
+                .map(|te| te.trait_tree_node.id)
         };
+        let row = self
+            .all_talents
+            .iter()
+            .filter(|te| te.trait_tree_node.tier() == node.tier())
+            .filter(|te| self.layout_section(&te.trait_tree_node) == section);
 
-        match entry.trait_tree_node.trait_sub_tree_id {
-            Some(_) => self.scale(
-                entry.trait_tree_node.pos_x + x_offset,
-                entry.trait_tree_node.pos_y + y_offset,
+        Neighbours {
+            up: closest(node.prev.iter().filter_map(|id| self.talent(*id)).collect()),
+            down: closest(node.next.iter().filter_map(|id| self.talent(*id)).collect()),
+            left: closest(
+                row.clone()
+                    .filter(|te| te.trait_tree_node.pos_x < node.pos_x)
+                    .collect(),
+            ),
+            right: closest(
+                row.filter(|te| te.trait_tree_node.pos_x > node.pos_x)
+                    .collect(),
             ),
-            None => self.scale(entry.trait_tree_node.pos_x, entry.trait_tree_node.pos_y),
         }
     }
 
-    fn do_draw(&self, node: &TalentEntry) -> bool {
+    fn aria_label(&self, talent: &TalentEntry) -> String {
+        let name = talent
+            .trait_tree_entry
+            .name
+            .clone()
+            .unwrap_or_else(|| talent.trait_tree_node.name.clone());
+        let state = match self.node_state(talent) {
+            _ if talent.trait_tree_node.is_free() => "granted",
+            NodeState::Allocated => "allocated",
+            NodeState::Available => "available",
+            NodeState::Locked => "locked",
+        };
+        let mut label = format!(
+            "{name}, rank {} of {}, {state}",
+            talent.rank,
+            talent.max_ranks()
+        );
+
+        let entries = &talent.trait_tree_node.entries;
+        if talent.trait_tree_node.node_type == TraitTreeNodeType::Choice {
+            let position = entries
+                .iter()
+                .position(|tte| tte.id == talent.trait_tree_entry.id)
+                .unwrap_or(0);
+            label.push_str(&format!(", choice {} of {}", position + 1, entries.len()));
+        }
+        label
+    }
+
+    fn coordinate_transformation(&self, entry: &TalentEntry, ctx: &DrawContext) -> (i32, i32) {
+        let node = &entry.trait_tree_node;
+        ctx.layout
+            .position(self.layout_section(node), ctx.settings.node_position(node))
+    }
+
+    fn do_draw(&self, node: &TalentEntry, ctx: &DrawContext) -> bool {
         if node.trait_tree_node.id == 0 {
             return false;
         }
@@ -320,7 +792,7 @@ impl TalentConfiguration {
 
         match node.trait_tree_node.trait_sub_tree_id {
             Some(tst_id) => {
-                if !self.subtrees.contains(&tst_id) {
+                if !ctx.hero_trees.contains(&tst_id) {
                     return false;
                 }
             }
@@ -330,108 +802,908 @@ impl TalentConfiguration {
         true
     }
 
-    fn draw_node(&self, node: &TalentEntry) -> impl IntoView {
-        if !self.do_draw(node) {
+    fn draw_node(&self, node: &TalentEntry, ctx: &DrawContext) -> impl IntoView {
+        if !self.do_draw(node, ctx) {
             return Either::Right(view! {});
         }
 
-        let (cx, cy) = self.coordinate_transformation(node);
-        let name = match &node.trait_tree_entry.name {
-            Some(n) => n.to_string(),
-            None => node.trait_tree_node.name.to_string(),
+        let (cx, cy) = self.coordinate_transformation(node, ctx);
+        let tooltip = node.tooltip();
+        let id = ctx.node_dom_id(node.trait_tree_node.id);
+        let color = self.node_color(node, &ctx.theme);
+        let rank_badge = format!("{}/{}", node.rank, node.max_ranks());
+        let label_font_size = ctx.theme.label_font_size;
+        let aria_label = self.aria_label(node);
+
+        let node_id = node.trait_tree_node.id;
+        let neighbours = self.neighbours(node, ctx);
+        let on_action = ctx.on_action;
+        let id_prefix = ctx.id_prefix.clone();
+        // enter on a taken choice node switches the choice instead
+        let primary_action = match (&node.trait_tree_node.node_type, node.is_maxed()) {
+            (TraitTreeNodeType::Choice, true) => NodeAction::CycleChoice,
+            _ => NodeAction::Allocate,
+        };
+        let on_keydown = move |ev: KeyboardEvent| {
+            let key = ev.key();
+            let action = match key.as_str() {
+                "Enter" | " " => Some(primary_action),
+                "Backspace" | "Delete" => Some(NodeAction::Deallocate),
+                _ => None,
+            };
+            match (neighbours.for_key(&key), action, on_action) {
+                (Some(target), _, _) => focus_node(&id_prefix, target),
+                (None, Some(action), Some(on_action)) => on_action.run((node_id, action)),
+                _ => return,
+            }
+            ev.prevent_default();
         };
-        let id = node.trait_tree_node.id.to_string();
-        let color = match node.rank {
-            0 => "red",
-            _ => "green",
+
+        let shape = match node.trait_tree_node.node_type {
+            TraitTreeNodeType::Choice => {
+                Either::Left(self.draw_choice_node(node, &id, (cx, cy), &color, ctx))
+            }
+            _ => Either::Right(self.draw_single_node(node, &id, (cx, cy), &color, ctx)),
         };
+        let ring = ctx.highlight.contains(node_id).then(|| {
+            view! {
+                <circle
+                    cx=cx
+                    cy=cy
+                    r=NODE_RADIUS + 4
+                    fill="none"
+                    stroke=ctx.theme.highlight.clone()
+                    stroke-width=ctx.theme.node_stroke_width
+                />
+            }
+        });
 
-        // TODO: unique ids even if multiple talent trees of the same spec are rendered
         Either::Left(view! {
-            <Tooltip content=name appearance=TooltipAppearance::Normal>
-                <circle cx=cx cy=cy r=10 fill=color id=id />
+            <Tooltip content=tooltip appearance=TooltipAppearance::Normal>
+                <g
+                    id=id
+                    class="talent-node"
+                    tabindex=0
+                    role="button"
+                    aria-label=aria_label
+                    on:keydown=on_keydown
+                >
+                    {ring}
+                    {shape}
+                    <text
+                        x=cx + NODE_RADIUS
+                        y=cy + NODE_RADIUS
+                        font-size=label_font_size
+                        text-anchor="middle"
+                        fill=color
+                    >
+                        {rank_badge}
+                    </text>
+                </g>
             </Tooltip>
         })
     }
 
-    fn draw_nodes(&self) -> impl IntoView {
+    fn draw_single_node(
+        &self,
+        node: &TalentEntry,
+        id: &str,
+        (cx, cy): (i32, i32),
+        color: &str,
+        ctx: &DrawContext,
+    ) -> AnyView {
+        let clip_id = format!("{id}-clip");
+        let icon = ctx
+            .icons
+            .icon_url(node.trait_tree_entry.spell_id.or_else(|| {
+                node.trait_tree_node
+                    .entries
+                    .first()
+                    .and_then(|entry| entry.spell_id)
+            }));
+        let opacity = match node.rank {
+            0 => 0.5,
+            _ => 1.0,
+        };
+
+        // actives are squares, passives are circles
+        let outline = match node.trait_tree_entry.node_type {
+            Some(TraitTreeEntryType::Active) => Either::Left(view! {
+                <rect
+                    x=cx - NODE_RADIUS
+                    y=cy - NODE_RADIUS
+                    width=2 * NODE_RADIUS
+                    height=2 * NODE_RADIUS
+                    rx=2
+                />
+            }),
+            _ => Either::Right(view! { <circle cx=cx cy=cy r=NODE_RADIUS /> }),
+        };
+
+        view! {
+            <clipPath id=clip_id.clone()>{outline.clone()}</clipPath>
+            {draw_icon(icon, (cx, cy), &clip_id, opacity, color)}
+            <g
+                fill="none"
+                stroke=color.to_string()
+                stroke-width=ctx.theme.node_stroke_width
+                stroke-dasharray=granted_dash(node)
+            >
+                {outline}
+            </g>
+        }
+        .into_any()
+    }
+
+    fn draw_choice_node(
+        &self,
+        node: &TalentEntry,
+        id: &str,
+        (cx, cy): (i32, i32),
+        color: &str,
+        ctx: &DrawContext,
+    ) -> AnyView {
+        // octagon split into one half per choice entry
+        let halves = node
+            .trait_tree_node
+            .entries
+            .iter()
+            .take(2)
+            .enumerate()
+            .map(|(index, entry)| {
+                let clip_id = format!("{id}-clip-{index}");
+                let points = points_attr(&octagon_half(cx, cy, index == 0));
+                let chosen = node.rank > 0 && entry.id == node.trait_tree_entry.id;
+                let opacity = match chosen {
+                    true => 1.0,
+                    false => 0.5,
+                };
+                view! {
+                    <clipPath id=clip_id.clone()>
+                        <polygon points=points />
+                    </clipPath>
+                    {draw_icon(ctx.icons.icon_url(entry.spell_id), (cx, cy), &clip_id, opacity, color)}
+                }
+            })
+            .collect::<Vec<_>>();
+
+        view! {
+            {halves}
+            <polygon
+                points=points_attr(&octagon(cx, cy))
+                fill="none"
+                stroke=color.to_string()
+                stroke-width=ctx.theme.node_stroke_width
+                stroke-dasharray=granted_dash(node)
+            />
+            <line
+                x1=cx
+                y1=cy - NODE_RADIUS
+                x2=cx
+                y2=cy + NODE_RADIUS
+                stroke=ctx.theme.choice.clone()
+                stroke-width=ctx.theme.node_stroke_width / 2.0
+            />
+        }
+        .into_any()
+    }
+
+    fn draw_nodes(&self, ctx: &DrawContext) -> impl IntoView {
         self.all_talents
             .iter()
-            .map(|entry| self.draw_node(entry))
+            .map(|entry| self.draw_node(entry, ctx))
             .collect::<Vec<_>>()
     }
 
-    fn draw_line(&self, a: &TalentEntry, b: &TalentEntry) -> impl IntoView {
-        if !self.do_draw(a) || !self.do_draw(b) {
+    // points spent in the node's section on rows above it, which is what `req_points` gates on
+    pub fn points_spent_before(&self, node: &TraitTreeNode) -> usize {
+        let section = self.layout_section(node);
+        self.selected_talents
+            .iter()
+            .filter(|te| !te.trait_tree_node.is_free())
+            .filter(|te| te.trait_tree_node.tier() < node.tier())
+            .filter(|te| self.layout_section(&te.trait_tree_node) == section)
+            .map(|te| te.rank)
+            .sum()
+    }
+
+    pub fn gate_unlocked(&self, node: &TraitTreeNode) -> bool {
+        node.req_points
+            .is_none_or(|req_points| self.points_spent_before(node) >= req_points)
+    }
+
+    // granted nodes cost nothing
+    pub fn points_spent(&self, section: LayoutSection) -> usize {
+        self.selected_talents
+            .iter()
+            .filter(|te| !te.trait_tree_node.is_free())
+            .filter(|te| self.layout_section(&te.trait_tree_node) == section)
+            .map(|te| te.rank)
+            .sum()
+    }
+
+    pub fn point_budget(&self, section: LayoutSection) -> usize {
+        let budget = &self.trait_tree.point_budget;
+        match section {
+            LayoutSection::Class => budget.class,
+            LayoutSection::Spec => budget.spec,
+            LayoutSection::Hero(_) => budget.hero,
+        }
+    }
+
+    // one gate per section and threshold, on the first row that requires it
+    pub fn gates(&self) -> Vec<Gate> {
+        let mut gates: Vec<Gate> = Vec::new();
+        for node in self.all_talents.iter().map(|te| &te.trait_tree_node) {
+            let Some(req_points) = node.req_points.filter(|req_points| *req_points > 0) else {
+                continue;
+            };
+            let section = self.layout_section(node);
+            match gates
+                .iter_mut()
+                .find(|gate| gate.section == section && gate.req_points == req_points)
+            {
+                Some(gate) if gate.tier <= node.tier() => (),
+                Some(gate) => *gate = Gate::new(self, node, section, req_points),
+                None => gates.push(Gate::new(self, node, section, req_points)),
+            }
+        }
+        gates
+    }
+
+    pub fn edge_state(&self, a: &TalentEntry, b: &TalentEntry) -> EdgeState {
+        match (a.is_maxed(), b.rank > 0) {
+            (true, true) => EdgeState::Taken,
+            (true, false) => EdgeState::Available,
+            (false, _) => EdgeState::Blocked,
+        }
+    }
+
+    fn draw_line(&self, a: &TalentEntry, b: &TalentEntry, ctx: &DrawContext) -> impl IntoView {
+        if !self.do_draw(a, ctx) || !self.do_draw(b, ctx) {
             return Either::Right(view! {});
         }
 
-        let (x_1, y_1) = self.coordinate_transformation(a);
-        let (x_2, y_2) = self.coordinate_transformation(b);
-        let color = match a.rank > 0 && b.rank > 0 {
-            true => "green",
-            false => "red",
+        let (x_1, y_1) = self.coordinate_transformation(a, ctx);
+        let (x_2, y_2) = self.coordinate_transformation(b, ctx);
+
+        // stop at the node outlines so the arrowhead stays visible
+        let (dx, dy) = ((x_2 - x_1) as f64, (y_2 - y_1) as f64);
+        let length = dx.hypot(dy).max(f64::EPSILON);
+        let (ux, uy) = (dx / length, dy / length);
+        let r = (NODE_RADIUS as f64).min(length / 2.0);
+
+        let state = self.edge_state(a, b);
+        let highlighted = ctx.highlight.path.contains(&a.trait_tree_node.id)
+            && ctx.highlight.path.contains(&b.trait_tree_node.id);
+        let (color, stroke_width) = match highlighted {
+            true => (
+                ctx.theme.highlight.clone(),
+                2.0 * ctx.theme.edge_stroke_width,
+            ),
+            false => (edge_color(state, &ctx.theme), ctx.theme.edge_stroke_width),
+        };
+        let marker_end = format!("url(#{})", ctx.marker_dom_id(state));
+        // edges into sections that are still gated are dimmed
+        let opacity = match self.gate_unlocked(&b.trait_tree_node) {
+            true => 1.0,
+            false => 0.3,
         };
 
-        Either::Left(view! { <line x1=x_1 y1=y_1 x2=x_2 y2=y_2 stroke=color /> })
+        Either::Left(view! {
+            <line
+                x1=x_1 as f64 + ux * r
+                y1=y_1 as f64 + uy * r
+                x2=x_2 as f64 - ux * r
+                y2=y_2 as f64 - uy * r
+                stroke=color
+                stroke-width=stroke_width
+                opacity=opacity
+                marker-end=marker_end
+            />
+        })
     }
 
-    fn draw_lines(&self) -> impl IntoView {
+    fn draw_lines(&self, ctx: &DrawContext) -> impl IntoView {
         self.all_talents
             .iter()
             .flat_map(|entry| {
-                entry.trait_tree_node.next.iter().map(|b_id| {
-                    let b = self
-                        .all_talents
-                        .iter()
-                        .find(|te| te.trait_tree_node.id == *b_id)
-                        .unwrap();
-                    self.draw_line(entry, b)
+                entry.trait_tree_node.next.iter().filter_map(|b_id| {
+                    // edges to nodes missing from the data are skipped
+                    self.talent(*b_id).map(|b| self.draw_line(entry, b, ctx))
+                })
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn draw_gates(&self, ctx: &DrawContext) -> impl IntoView {
+        self.gates()
+            .into_iter()
+            .filter_map(|gate| {
+                let section = ctx.layout.sections.get(&gate.section)?;
+                let (_, y) = ctx.layout.position(
+                    gate.section,
+                    (0, ctx.settings.row_y(gate.node_tier, gate.pos_y)),
+                );
+                let y = y - NODE_RADIUS - 4;
+                let (x_1, x_2) = (section.min_x - NODE_RADIUS, section.max_x + NODE_RADIUS);
+                let color = match gate.is_unlocked() {
+                    true => ctx.theme.muted.clone(),
+                    false => ctx.theme.locked.clone(),
+                };
+                // everything below a locked gate is shaded
+                let shade = (!gate.is_unlocked()).then(|| {
+                    view! {
+                        <rect
+                            x=x_1
+                            y=y
+                            width=x_2 - x_1
+                            height=section.max_y + NODE_RADIUS - y
+                            fill=ctx.theme.muted.clone()
+                            opacity=0.15
+                        />
+                    }
+                });
+                Some(view! {
+                    {shade}
+                    <line
+                        x1=x_1
+                        y1=y
+                        x2=x_2
+                        y2=y
+                        stroke=color.clone()
+                        stroke-dasharray="4 2"
+                    />
+                    <text x=x_1 y=y - 2 font-size=ctx.theme.label_font_size fill=color>
+                        {format!("{} points required", gate.req_points)}
+                    </text>
                 })
             })
             .collect::<Vec<_>>()
     }
 
-    pub fn draw(&self) -> impl IntoView {
+    fn draw_point_counters(&self, ctx: &DrawContext) -> impl IntoView {
+        let hero_names = self.hero_trees();
+        ctx.layout
+            .sections
+            .iter()
+            .map(|(section, bounds)| {
+                let label = match section {
+                    LayoutSection::Class => self.trait_tree.class_name.clone(),
+                    LayoutSection::Spec => self.trait_tree.spec_name.clone(),
+                    LayoutSection::Hero(tst_id) => hero_names
+                        .iter()
+                        .find(|(id, _)| id == tst_id)
+                        .map(|(_, name)| name.clone())
+                        .unwrap_or_default(),
+                };
+                let (spent, budget) = (self.points_spent(*section), self.point_budget(*section));
+                let color = match (spent > budget, section) {
+                    (true, _) => ctx.theme.locked.clone(),
+                    (false, LayoutSection::Hero(_)) => ctx.theme.hero.clone(),
+                    (false, _) => ctx.theme.text.clone(),
+                };
+                view! {
+                    <text
+                        x=(bounds.min_x + bounds.max_x) / 2
+                        y=bounds.min_y - NODE_RADIUS - 6
+                        font-size=ctx.theme.heading_font_size
+                        text-anchor="middle"
+                        fill=color
+                    >
+                        {format!("{label} {spent}/{budget}")}
+                    </text>
+                }
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn draw_markers(&self, ctx: &DrawContext) -> impl IntoView {
+        [EdgeState::Taken, EdgeState::Available, EdgeState::Blocked]
+            .into_iter()
+            .map(|state| {
+                let color = edge_color(state, &ctx.theme);
+                view! {
+                    <marker
+                        id=ctx.marker_dom_id(state)
+                        view_box="0 0 10 10"
+                        refX=10
+                        refY=5
+                        markerWidth=4
+                        markerHeight=4
+                        orient="auto"
+                    >
+                        <path d="M 0 0 L 10 5 L 0 10 z" fill=color />
+                    </marker>
+                }
+            })
+            .collect::<Vec<_>>()
+    }
+
+    pub fn draw(&self, options: DrawOptions, viewport: Signal<Viewport>) -> impl IntoView {
+        let hero_trees = self.visible_hero_trees(options.hero_tree_display);
+        let layout = self.compute_layout(&hero_trees, &options.layout);
+        let bounds = layout.bounds;
+        let ctx = DrawContext {
+            hero_trees,
+            icons: options.icons,
+            settings: options.layout,
+            layout,
+            on_action: options.on_action,
+            theme: options.theme,
+            id_prefix: options.id_prefix,
+            highlight: options.highlight,
+        };
+        let label = format!(
+            "{} {} talent tree",
+            self.trait_tree.spec_name, self.trait_tree.class_name
+        );
+
+        // scales down to the available width, never up past the natural size
         view! {
-            <svg view_box="0 0 1500 500" height=500 width=1500>
-                {self.draw_lines()}
-                {self.draw_nodes()}
+            <svg
+                view_box=move || viewport.get().view_box(&bounds)
+                width="100%"
+                style=format!("max-width: {}px", bounds.width())
+                role="group"
+                aria-label=label
+                font-family=ctx.theme.font_family.clone()
+            >
+                <defs>{self.draw_markers(&ctx)}</defs>
+                <rect
+                    x=bounds.min_x
+                    y=bounds.min_y
+                    width=bounds.width()
+                    height=bounds.height()
+                    fill=ctx.theme.background.clone()
+                />
+                {self.draw_gates(&ctx)}
+                {self.draw_point_counters(&ctx)}
+                {self.draw_lines(&ctx)}
+                {self.draw_nodes(&ctx)}
             </svg>
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct DrawOptions {
+    pub hero_tree_display: HeroTreeDisplay,
+    pub layout: LayoutSettings,
+    pub icons: IconManifest,
+    // without a handler nodes can be focused and navigated but not changed
+    pub on_action: Option<Callback<(usize, NodeAction)>>,
+    pub theme: TreeTheme,
+    // every element id in the svg starts with this, see `next_id_prefix`
+    pub id_prefix: String,
+    pub highlight: Highlight,
+}
+
+// node ids drawn with a ring, edges between two path nodes are drawn in the same color
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Highlight {
+    pub nodes: HashSet<usize>,
+    pub path: HashSet<usize>,
+}
+
+impl Highlight {
+    fn contains(&self, node_id: usize) -> bool {
+        self.nodes.contains(&node_id) || self.path.contains(&node_id)
+    }
+}
+
+const NODE_RADIUS: i32 = 10;
+
+// nodes missing from the data decode as id 0 and are left out
+fn index_talents(talents: &[TalentEntry]) -> HashMap<usize, usize> {
+    let mut index = HashMap::with_capacity(talents.len());
+    for (position, talent) in talents.iter().enumerate() {
+        if talent.trait_tree_node.id != 0 {
+            index.entry(talent.trait_tree_node.id).or_insert(position);
+        }
+    }
+    index
+}
+
+fn edge_color(state: EdgeState, theme: &TreeTheme) -> String {
+    match state {
+        EdgeState::Taken => theme.selected.clone(),
+        EdgeState::Available => theme.unselected.clone(),
+        EdgeState::Blocked => theme.locked.clone(),
+    }
+}
+
+fn edge_state_name(state: EdgeState) -> &'static str {
+    match state {
+        EdgeState::Taken => "taken",
+        EdgeState::Available => "available",
+        EdgeState::Blocked => "blocked",
+    }
+}
+
+// per-render state shared by the draw methods
+struct DrawContext {
+    hero_trees: Vec<usize>,
+    icons: IconManifest,
+    settings: LayoutSettings,
+    layout: TreeLayout,
+    on_action: Option<Callback<(usize, NodeAction)>>,
+    theme: TreeTheme,
+    id_prefix: String,
+    highlight: Highlight,
+}
+
+impl DrawContext {
+    fn node_dom_id(&self, node_id: usize) -> String {
+        node_dom_id(&self.id_prefix, node_id)
+    }
+
+    fn marker_dom_id(&self, state: EdgeState) -> String {
+        format!("{}-arrow-{}", self.id_prefix, edge_state_name(state))
+    }
+}
+
+// ids are document wide, trees rendered side by side would otherwise share clip paths and markers
+pub fn next_id_prefix() -> String {
+    static NEXT_TREE: AtomicUsize = AtomicUsize::new(0);
+    format!("tree-{}", NEXT_TREE.fetch_add(1, Ordering::Relaxed))
+}
+
+fn node_dom_id(id_prefix: &str, node_id: usize) -> String {
+    format!("{id_prefix}-node-{node_id}")
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Neighbours {
+    up: Option<usize>,
+    down: Option<usize>,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl Neighbours {
+    fn for_key(&self, key: &str) -> Option<usize> {
+        match key {
+            "ArrowUp" => self.up,
+            "ArrowDown" => self.down,
+            "ArrowLeft" => self.left,
+            "ArrowRight" => self.right,
+            _ => None,
+        }
+    }
+}
+
+fn focus_node(id_prefix: &str, node_id: usize) {
+    let Some(element) = document().get_element_by_id(&node_dom_id(id_prefix, node_id)) else {
+        return;
+    };
+    if let Ok(element) = element.dyn_into::<web_sys::SvgElement>() {
+        let _ = element.focus();
+    }
+}
+
+fn draw_icon(
+    icon: Option<String>,
+    (cx, cy): (i32, i32),
+    clip_id: &str,
+    opacity: f64,
+    color: &str,
+) -> impl IntoView {
+    let clip_path = format!("url(#{clip_id})");
+    match icon {
+        Some(href) => Either::Left(view! {
+            <image
+                href=href
+                x=cx - NODE_RADIUS
+                y=cy - NODE_RADIUS
+                width=2 * NODE_RADIUS
+                height=2 * NODE_RADIUS
+                clip-path=clip_path
+                opacity=opacity
+            />
+        }),
+        // no icon known for this spell, fill the shape instead
+        None => Either::Right(view! {
+            <rect
+                x=cx - NODE_RADIUS
+                y=cy - NODE_RADIUS
+                width=2 * NODE_RADIUS
+                height=2 * NODE_RADIUS
+                clip-path=clip_path
+                fill=color.to_string()
+                opacity=opacity / 2.0
+            />
+        }),
+    }
+}
+
+// granted nodes are dashed, so they stand out without relying on color
+fn granted_dash(node: &TalentEntry) -> &'static str {
+    match node.trait_tree_node.is_free() {
+        true => "3 2",
+        false => "none",
+    }
+}
+
+// regular octagon with flat top and bottom edges, clockwise from the top left
+fn octagon(cx: i32, cy: i32) -> Vec<(f64, f64)> {
+    let (cx, cy, r) = (cx as f64, cy as f64, NODE_RADIUS as f64);
+    let a = r * std::f64::consts::FRAC_PI_8.tan();
+    vec![
+        (cx - a, cy - r),
+        (cx + a, cy - r),
+        (cx + r, cy - a),
+        (cx + r, cy + a),
+        (cx + a, cy + r),
+        (cx - a, cy + r),
+        (cx - r, cy + a),
+        (cx - r, cy - a),
+    ]
+}
+
+fn octagon_half(cx: i32, cy: i32, left: bool) -> Vec<(f64, f64)> {
+    let (cx, cy, r) = (cx as f64, cy as f64, NODE_RADIUS as f64);
+    let a = r * std::f64::consts::FRAC_PI_8.tan();
+    let side = match left {
+        true => -1.0,
+        false => 1.0,
+    };
+    vec![
+        (cx, cy - r),
+        (cx + side * a, cy - r),
+        (cx + side * r, cy - a),
+        (cx + side * r, cy + a),
+        (cx + side * a, cy + r),
+        (cx, cy + r),
+    ]
+}
+
+fn points_attr(points: &[(f64, f64)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{x:.2},{y:.2}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[component]
 pub fn DrawTalentConfigView(
     talent_configuration: Memo<Result<TalentConfiguration, TalentConfigurationError>>,
+    #[prop(optional, into)] hero_tree_display: Signal<HeroTreeDisplay>,
+    #[prop(optional)] layout: LayoutSettings,
+    #[prop(optional, into)] on_action: Option<Callback<(usize, NodeAction)>>,
+    #[prop(optional, into)] highlight: Signal<Highlight>,
+    // thumbnails leave out the zoom and download controls
+    #[prop(optional)] compact: bool,
 ) -> impl IntoView {
+    // without a manifest nodes are drawn without icons
+    let icon_data = use_icon_data();
+    let icons = move || icon_data.get().and_then(Result::ok).unwrap_or_default();
+    // trees outside of a themed page use the light theme
+    let theme = use_context::<Signal<TreeTheme>>().unwrap_or_default();
+
+    let layout = StoredValue::new(layout);
+    let viewport = RwSignal::new(Viewport::default());
+    let viewport_ref = NodeRef::<leptos::html::Div>::new();
+    let drag_start = StoredValue::new(None::<(i32, i32)>);
+
+    // the tree is redrawn after every change, so focus is restored to the node that was changed
+    let changed_node = StoredValue::new(None::<usize>);
+    let id_prefix = StoredValue::new(next_id_prefix());
+    let on_action = on_action.map(|on_action| {
+        Callback::new(move |(node_id, action): (usize, NodeAction)| {
+            changed_node.set_value(Some(node_id));
+            on_action.run((node_id, action));
+        })
+    });
+    Effect::new(move |_| {
+        talent_configuration.track();
+        if let Some(node_id) = changed_node.get_value() {
+            let id_prefix = id_prefix.get_value();
+            request_animation_frame(move || focus_node(&id_prefix, node_id));
+        }
+    });
+
+    let on_pointer_move = move |ev: PointerEvent| {
+        let Some((start_x, start_y)) = drag_start.get_value() else {
+            return;
+        };
+        let Some(div) = viewport_ref.get() else {
+            return;
+        };
+        let (width, height) = (div.client_width().max(1), div.client_height().max(1));
+        viewport.update(|v| {
+            *v = v.pan_by(
+                (start_x - ev.client_x()) as f64 / width as f64,
+                (start_y - ev.client_y()) as f64 / height as f64,
+            )
+        });
+        drag_start.set_value(Some((ev.client_x(), ev.client_y())));
+    };
+
+    let export = Action::new_local(move |format: &ExportFormat| {
+        let format = *format;
+        let name = talent_configuration.with_untracked(|config| match config {
+            Ok(conf) => export_name(&conf.trait_tree.class_name, &conf.trait_tree.spec_name),
+            Err(_) => "talent-tree".to_string(),
+        });
+        async move {
+            let svg = viewport_ref
+                .get_untracked()
+                .and_then(|div| div.query_selector("svg").ok().flatten())
+                .ok_or(ExportError::NothingRendered)?;
+            export_tree(&svg, format, &name).await
+        }
+    });
+
     let fallback = move |_| {
         view! { <div>{format!("{:?}", talent_configuration.get())}</div> }
     };
 
-    view! {
-        <div>
+    let tree = move || {
+        view! {
             <ErrorBoundary fallback>
                 {move || {
                     talent_configuration
                         .with(|config| {
                             match config {
-                                Ok(conf) => Either::Left(conf.draw()),
+                                Ok(conf) => {
+                                    let options = DrawOptions {
+                                        hero_tree_display: hero_tree_display.get(),
+                                        layout: layout.get_value(),
+                                        icons: icons(),
+                                        on_action,
+                                        theme: theme.get(),
+                                        id_prefix: id_prefix.get_value(),
+                                        highlight: highlight.get(),
+                                    };
+                                    Either::Left(conf.draw(options, viewport.into()))
+                                }
                                 Err(e) => Either::Right(view! { {format!("{:?}", e)} }),
                             }
                         })
                 }}
             </ErrorBoundary>
+        }
+    };
+
+    view! {
+        <div>
+            {(!compact)
+                .then(|| {
+                    view! {
+                        <div>
+                            <button on:click=move |_| viewport.update(|v| *v = v.zoom_by(1.25))>"+"</button>
+                            <button on:click=move |_| viewport.update(|v| *v = v.zoom_by(0.8))>"-"</button>
+                            <button on:click=move |_| viewport.set(Viewport::default())>"Reset view"</button>
+                            <button on:click=move |_| {
+                                export.dispatch(ExportFormat::Svg);
+                            }>"Download SVG"</button>
+                            <button on:click=move |_| {
+                                export.dispatch(ExportFormat::Png);
+                            }>"Download PNG"</button>
+                            {move || match export.value().get() {
+                                Some(Err(err)) => Some(view! { <span>{err.to_string()}</span> }),
+                                _ => None,
+                            }}
+                        </div>
+                    }
+                })}
+            {match compact {
+                true => Either::Left(view! { <div>{tree}</div> }),
+                // the wheel scrolls the page until the tree is zoomed in, ctrl + wheel zooms in
+                false => {
+                    Either::Right(
+                        view! {
+                            <div
+                                class="talent-tree-viewport"
+                                class:zoomed=move || viewport.get().is_zoomed()
+                                node_ref=viewport_ref
+                                on:wheel=move |ev: WheelEvent| {
+                                    if !ev.ctrl_key() && !viewport.get_untracked().is_zoomed() {
+                                        return;
+                                    }
+                                    ev.prevent_default();
+                                    let factor = match ev.delta_y() < 0.0 {
+                                        true => 1.1,
+                                        false => 1.0 / 1.1,
+                                    };
+                                    viewport.update(|v| *v = v.zoom_by(factor));
+                                }
+                                on:pointerdown=move |ev: PointerEvent| {
+                                    if viewport.get_untracked().is_zoomed() {
+                                        drag_start.set_value(Some((ev.client_x(), ev.client_y())))
+                                    }
+                                }
+                                on:pointermove=on_pointer_move
+                                on:pointerup=move |_| drag_start.set_value(None)
+                                on:pointerleave=move |_| drag_start.set_value(None)
+                            >
+                                {tree}
+                            </div>
+                        },
+                    )
+                }
+            }}
         </div>
     }
 }
 
 #[component]
-pub fn TalentConfigView(talent_encoding: ReadSignal<TalentEncoding>) -> impl IntoView {
-    let (talent_str, set_talent_str) = signal("CwQAAAAAAAAAAAAAAAAAAAAAAAAAAgZZzYGzYWmx2YmZMAAAAAAAWAxMDmhZsYGsNzMjZMMzsMLm22sNbzMD2AAgNEAAAz2s0MzMLMYD".to_string());
+pub fn HeroTreeSwitcher(
+    talent_configuration: Memo<Result<TalentConfiguration, TalentConfigurationError>>,
+    talent_encoding: ReadSignal<TalentEncoding>,
+    set_talent_str: WriteSignal<String>,
+    hero_tree_display: RwSignal<HeroTreeDisplay>,
+) -> impl IntoView {
+    let select_hero_tree = move |trait_sub_tree_id: usize| {
+        if let Ok(mut conf) = talent_configuration.get() {
+            conf.select_hero_tree(trait_sub_tree_id, &talent_encoding.get());
+            set_talent_str.set(conf.string);
+        }
+    };
+
+    let hero_trees = move || {
+        talent_configuration.with(|config| match config {
+            Ok(conf) => conf
+                .hero_trees()
+                .into_iter()
+                .map(|(id, name)| (id, name, conf.subtrees.contains(&id)))
+                .collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        })
+    };
 
-    let trait_tree_data = LocalResource::new(move || fetch_trait_trees());
+    view! {
+        <div>
+            {move || {
+                hero_trees()
+                    .into_iter()
+                    .map(|(id, name, selected)| {
+                        view! {
+                            <label>
+                                <input
+                                    type="radio"
+                                    name="hero-tree"
+                                    prop:checked=selected
+                                    on:change=move |_| select_hero_tree(id)
+                                />
+                                {name}
+                            </label>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || hero_tree_display.get() == HeroTreeDisplay::Both
+                    on:change:target=move |tag| {
+                        hero_tree_display
+                            .set(
+                                match tag.target().checked() {
+                                    true => HeroTreeDisplay::Both,
+                                    false => HeroTreeDisplay::Selected,
+                                },
+                            )
+                    }
+                />
+                "Show both hero trees"
+            </label>
+        </div>
+    }
+}
+
+#[component]
+pub fn TalentConfigView(
+    talent_encoding: ReadSignal<TalentEncoding>,
+    talent_str: ReadSignal<String>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let trait_tree_data = use_trait_tree_data();
+    let hero_tree_display = RwSignal::new(HeroTreeDisplay::default());
+    let highlight = RwSignal::new(Highlight::default());
+    let search_selection = RwSignal::new(None::<usize>);
 
     let fallback = || view! { <div>"Loading..."</div> };
 
@@ -439,7 +1711,7 @@ pub fn TalentConfigView(talent_encoding: ReadSignal<TalentEncoding>) -> impl Int
         <input
             type="text"
             on:input:target=move |tag| set_talent_str.set(tag.target().value())
-            value=talent_str
+            prop:value=talent_str
         />
         <Transition fallback>
             {move || Suspend::new(async move {
@@ -453,9 +1725,106 @@ pub fn TalentConfigView(talent_encoding: ReadSignal<TalentEncoding>) -> impl Int
                                 trait_trees.clone(),
                             )
                         });
-                        view! { <DrawTalentConfigView talent_configuration /> }
+                        let on_action = move |node_id: usize, action: NodeAction| {
+                            if let Ok(mut conf) = talent_configuration.get_untracked() {
+                                if conf.apply(node_id, action, &talent_encoding.get_untracked()) {
+                                    set_talent_str.set(conf.string);
+                                }
+                            }
+                        };
+                        view! {
+                            <HeroTreeSwitcher
+                                talent_configuration
+                                talent_encoding
+                                set_talent_str
+                                hero_tree_display
+                            />
+                            <TalentSearch talent_configuration highlight selected=search_selection />
+                            <TalentPathView
+                                talent_configuration
+                                talent_encoding
+                                set_talent_str
+                                target=search_selection
+                                highlight
+                            />
+                            <DrawTalentConfigView
+                                talent_configuration
+                                hero_tree_display
+                                on_action
+                                highlight
+                            />
+                        }
                     })
             })}
         </Transition>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tree::{empty_configuration, test_tree, SPEC_ID};
+
+    #[test]
+    fn new_needs_a_known_spec() {
+        let config = TalentEncoding::default();
+        assert_eq!(
+            TalentConfiguration::new(SPEC_ID + 1, config, vec![test_tree()]),
+            Err(TalentConfigurationError::SpecNotFound)
+        );
+    }
+
+    #[test]
+    fn granted_choice_nodes_are_not_purchased() {
+        let config = TalentEncoding::default();
+        let mut trait_tree = test_tree();
+        let split_path = trait_tree
+            .class_nodes
+            .iter_mut()
+            .find(|node| node.id == 3)
+            .unwrap();
+        split_path.free_node = Some(true);
+
+        let granted =
+            TalentConfiguration::new(SPEC_ID, config.clone(), vec![trait_tree.clone()]).unwrap();
+        let mut flipped = granted.clone();
+        assert!(flipped.apply(3, NodeAction::CycleChoice, &config));
+        // without a purchased bit there is nowhere to write the option
+        assert_eq!(flipped.string, granted.string);
+
+        let decoded =
+            TalentConfiguration::new_from_str(&flipped.string, config.clone(), vec![trait_tree])
+                .unwrap();
+        let talent = decoded.talent(3).unwrap();
+        assert_eq!((talent.rank, talent.trait_tree_entry.id), (1, Some(30)));
+        assert_eq!(decoded.encode(&config), granted.string);
+    }
+
+    #[test]
+    fn hero_tree_options_need_a_hero_tree() {
+        let config = TalentEncoding::default();
+        let mut configuration = empty_configuration();
+        configuration.select_hero_tree(51, &config);
+        let mut trait_tree = test_tree();
+        trait_tree.sub_tree_nodes[0].entries[1].trait_sub_tree_id = None;
+
+        assert_eq!(
+            TalentConfiguration::new_from_str(&configuration.string, config, vec![trait_tree]),
+            Err(TalentConfigurationError::SubTreeWithoutId {
+                node_id: 9,
+                entry_id: Some(91),
+            })
+        );
+    }
+
+    #[test]
+    fn spec_is_read_from_the_header() {
+        let config = TalentEncoding::default();
+        let configuration = empty_configuration();
+        assert_eq!(
+            TalentConfiguration::spec_from_str(&configuration.string, &config),
+            Ok(SPEC_ID)
+        );
+        assert!(TalentConfiguration::spec_from_str("C!", &config).is_err());
+    }
+}
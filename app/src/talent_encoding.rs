@@ -13,6 +13,8 @@ pub enum TalentEncodingError {
     StringTooShort,
     #[error("Serialization version does not match encoding configuration")]
     IncorrectSerializationVersion,
+    #[error("Talent string was created for a different talent tree version")]
+    TreeHashMismatch,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -54,7 +56,7 @@ impl TalentEncoding {
         rv
     }
 
-    fn valid_base64(&self, string: &str) -> Result<(), TalentEncodingError> {
+    pub fn valid_base64(&self, string: &str) -> Result<(), TalentEncodingError> {
         let match_str = format!(r"[^{}]+", self.escaped_chars());
         let re = Regex::new(match_str.as_str()).unwrap();
         match re.find(string) {
@@ -77,6 +79,19 @@ impl TalentEncoding {
         }
     }
 
+    // an all-zero hash is written by tools that do not know the tree hash, and is not checked
+    pub fn valid_tree_hash(
+        &self,
+        string_hash: &[u8],
+        tree_hash: &[u8],
+    ) -> Result<(), TalentEncodingError> {
+        let expected = tree_hash.iter().take(string_hash.len());
+        match string_hash.iter().all(|byte| *byte == 0) || string_hash.iter().eq(expected) {
+            true => Ok(()),
+            false => Err(TalentEncodingError::TreeHashMismatch),
+        }
+    }
+
     pub fn is_valid(&self, string: &str, version: usize) -> Result<(), TalentEncodingError> {
         self.valid_base64(string)?;
         self.valid_size(string)?;
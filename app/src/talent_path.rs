@@ -0,0 +1,326 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use leptos::prelude::*;
+use thiserror::Error;
+
+use crate::configuration_generation::talent_name;
+use crate::talent_configuration::{
+    Highlight, NodeAction, TalentConfiguration, TalentConfigurationError,
+};
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::TraitTreeNodeType;
+
+/*
+ * The cheapest chain of prerequisites is found first, every node on it maxed and the target
+ * taken with one point. The chain is then allocated top down, where a gate is still locked the
+ * missing points go into the first available talents above it. When a section is out of points,
+ * points are refunded from the lowest rows first if refunds are allowed, never from the chain
+ * and never where it would strand another talent.
+ */
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TalentPathError {
+    #[error("Talent not found in this tree.")]
+    UnknownNode,
+    #[error("The talent is already taken.")]
+    AlreadyTaken,
+    #[error("The talent is in a hero tree that is not selected.")]
+    HeroTreeNotSelected,
+    #[error("No chain of prerequisites leads to the talent.")]
+    Unreachable,
+    #[error("Not enough points left, allow refunds to make room.")]
+    OverBudget,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TalentPath {
+    pub configuration: TalentConfiguration,
+    // prerequisites from the top down, the target last
+    pub chain: Vec<usize>,
+    // node id and points, in the order they were first spent or refunded
+    pub added: Vec<(usize, usize)>,
+    pub refunded: Vec<(usize, usize)>,
+}
+
+impl TalentPath {
+    pub fn points_added(&self) -> usize {
+        self.added.iter().map(|(_, points)| points).sum()
+    }
+
+    pub fn points_refunded(&self) -> usize {
+        self.refunded.iter().map(|(_, points)| points).sum()
+    }
+
+    pub fn highlight(&self) -> Highlight {
+        Highlight {
+            nodes: self.refunded.iter().map(|(id, _)| *id).collect(),
+            path: self
+                .chain
+                .iter()
+                .copied()
+                .chain(self.added.iter().map(|(id, _)| *id))
+                .collect(),
+        }
+    }
+}
+
+fn count(list: &mut Vec<(usize, usize)>, node_id: usize) {
+    match list.iter_mut().find(|(id, _)| *id == node_id) {
+        Some((_, points)) => *points += 1,
+        None => list.push((node_id, 1)),
+    }
+}
+
+impl TalentConfiguration {
+    // points still missing to max the node
+    fn points_to_max(&self, node_id: usize) -> Option<usize> {
+        self.talent(node_id)
+            .map(|te| te.max_ranks().saturating_sub(te.rank))
+    }
+
+    // cheapest prev chain from a node whose prerequisites are met down to the target
+    fn prerequisite_chain(&self, target: usize) -> Option<Vec<usize>> {
+        let hero_tree_selected = |node_id: usize| {
+            self.talent(node_id).is_some_and(|te| {
+                te.trait_tree_node
+                    .trait_sub_tree_id
+                    .is_none_or(|id| self.subtrees.contains(&id))
+            })
+        };
+
+        let mut cost = HashMap::from([(target, 0)]);
+        // the child each node was reached from
+        let mut reached_from = HashMap::new();
+        let mut queue = BinaryHeap::from([Reverse((0, target))]);
+        while let Some(Reverse((points, node_id))) = queue.pop() {
+            if cost.get(&node_id).is_some_and(|best| *best < points) {
+                continue;
+            }
+            let node = &self.talent(node_id)?.trait_tree_node;
+            if self.prerequisites_met(node) {
+                let mut chain = vec![node_id];
+                while let Some(child) = reached_from.get(chain.last().unwrap()) {
+                    chain.push(*child);
+                }
+                return Some(chain);
+            }
+            for &prev in node.prev.iter().filter(|id| hero_tree_selected(**id)) {
+                let Some(to_max) = self.points_to_max(prev) else {
+                    continue;
+                };
+                let points = points + to_max;
+                if cost.get(&prev).is_none_or(|best| points < *best) {
+                    cost.insert(prev, points);
+                    reached_from.insert(prev, node_id);
+                    queue.push(Reverse((points, prev)));
+                }
+            }
+        }
+        None
+    }
+
+    // first talent above the node's row that takes a point, for locked gates
+    fn gate_filler(&self, node_id: usize, skip: &HashSet<usize>) -> Option<usize> {
+        let node = &self.talent(node_id)?.trait_tree_node;
+        let section = self.layout_section(node);
+        self.all_talents
+            .iter()
+            .filter(|te| te.trait_tree_node.tier() < node.tier())
+            .filter(|te| te.trait_tree_node.node_type != TraitTreeNodeType::SubTree)
+            .filter(|te| !skip.contains(&te.trait_tree_node.id))
+            .filter(|te| self.layout_section(&te.trait_tree_node) == section)
+            .filter(|te| self.can_allocate(te))
+            .min_by_key(|te| (te.trait_tree_node.tier(), te.trait_tree_node.pos_x))
+            .map(|te| te.trait_tree_node.id)
+    }
+
+    // refunds one point in the node's section, lowest rows first
+    fn refund_one(
+        &mut self,
+        node_id: usize,
+        skip: &HashSet<usize>,
+        config: &TalentEncoding,
+    ) -> Option<usize> {
+        let section = self.layout_section(&self.talent(node_id)?.trait_tree_node);
+        let mut candidates = self
+            .selected_talents
+            .iter()
+            .map(|te| &te.trait_tree_node)
+            .filter(|node| !node.is_free() && !skip.contains(&node.id))
+            .filter(|node| self.layout_section(node) == section)
+            .map(|node| (node.tier(), node.pos_x, node.id))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates
+            .into_iter()
+            .rev()
+            .map(|(_, _, id)| id)
+            .find(|id| self.apply(*id, NodeAction::Deallocate, config))
+    }
+
+    pub fn path_to(
+        &self,
+        target: usize,
+        allow_refunds: bool,
+        config: &TalentEncoding,
+    ) -> Result<TalentPath, TalentPathError> {
+        let talent = self.talent(target).ok_or(TalentPathError::UnknownNode)?;
+        let node = &talent.trait_tree_node;
+        if node.node_type == TraitTreeNodeType::SubTree {
+            return Err(TalentPathError::UnknownNode);
+        }
+        if talent.rank > 0 {
+            return Err(TalentPathError::AlreadyTaken);
+        }
+        if node
+            .trait_sub_tree_id
+            .is_some_and(|id| !self.subtrees.contains(&id))
+        {
+            return Err(TalentPathError::HeroTreeNotSelected);
+        }
+        let chain = self
+            .prerequisite_chain(target)
+            .ok_or(TalentPathError::Unreachable)?;
+
+        let mut path = TalentPath {
+            configuration: self.clone(),
+            chain: chain.clone(),
+            added: Vec::new(),
+            refunded: Vec::new(),
+        };
+        let build = &mut path.configuration;
+        let mut spent_on = chain.iter().copied().collect::<HashSet<_>>();
+        let mut refunded_from = HashSet::new();
+        // every step spends or refunds a point, this bounds a search that keeps undoing itself
+        let mut steps = 4
+            * (build.trait_tree.point_budget.class
+                + build.trait_tree.point_budget.spec
+                + build.trait_tree.point_budget.hero)
+            + 4 * chain.len();
+
+        for &node_id in &chain {
+            let wanted = match node_id == target {
+                true => 1,
+                false => build.talent(node_id).map_or(0, |te| te.max_ranks()),
+            };
+            while build.talent(node_id).is_some_and(|te| te.rank < wanted) {
+                steps = steps.checked_sub(1).ok_or(TalentPathError::OverBudget)?;
+                let talent = build.talent(node_id).unwrap();
+                let node = &talent.trait_tree_node;
+                let section = build.layout_section(node);
+
+                if build.can_allocate(talent) {
+                    build.apply(node_id, NodeAction::Allocate, config);
+                    count(&mut path.added, node_id);
+                } else if build.points_spent(section) >= build.point_budget(section) {
+                    if !allow_refunds {
+                        return Err(TalentPathError::OverBudget);
+                    }
+                    let refunded = build
+                        .refund_one(node_id, &spent_on, config)
+                        .ok_or(TalentPathError::OverBudget)?;
+                    refunded_from.insert(refunded);
+                    count(&mut path.refunded, refunded);
+                } else if !build.gate_unlocked(node) {
+                    let filler = build
+                        .gate_filler(node_id, &refunded_from)
+                        .ok_or(TalentPathError::Unreachable)?;
+                    build.apply(filler, NodeAction::Allocate, config);
+                    spent_on.insert(filler);
+                    count(&mut path.added, filler);
+                } else {
+                    return Err(TalentPathError::Unreachable);
+                }
+            }
+        }
+        Ok(path)
+    }
+}
+
+#[component]
+pub fn TalentPathView(
+    talent_configuration: Memo<Result<TalentConfiguration, TalentConfigurationError>>,
+    talent_encoding: ReadSignal<TalentEncoding>,
+    set_talent_str: WriteSignal<String>,
+    // picked in the talent search
+    #[prop(into)] target: Signal<Option<usize>>,
+    highlight: RwSignal<Highlight>,
+) -> impl IntoView {
+    let (allow_refunds, set_allow_refunds) = signal(false);
+
+    let path = Memo::new(move |_| {
+        let target = target.get()?;
+        talent_configuration.with(|config| {
+            let conf = config.as_ref().ok()?;
+            Some(conf.path_to(target, allow_refunds.get(), &talent_encoding.get()))
+        })
+    });
+
+    let describe = move |points: &[(usize, usize)]| {
+        talent_configuration.with_untracked(|config| {
+            let Ok(conf) = config else {
+                return String::new();
+            };
+            points
+                .iter()
+                .filter_map(|(id, points)| {
+                    conf.talent(*id)
+                        .map(|te| format!("{} ({points})", talent_name(te)))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    };
+
+    view! {
+        <div class="talent-path">
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=allow_refunds
+                    on:change:target=move |tag| set_allow_refunds.set(tag.target().checked())
+                />
+                "Refund points to make room"
+            </label>
+            {move || {
+                path.get()
+                    .map(|result| match result {
+                        Err(err) => view! { <div>{err.to_string()}</div> }.into_any(),
+                        Ok(path) => {
+                            let string = path.configuration.string.clone();
+                            let shown = path.highlight();
+                            view! {
+                                <div>
+                                    {format!(
+                                        "{} points to spend: {}",
+                                        path.points_added(),
+                                        describe(&path.added),
+                                    )}
+                                </div>
+                                {(!path.refunded.is_empty())
+                                    .then(|| {
+                                        view! {
+                                            <div>
+                                                {format!(
+                                                    "{} points to refund: {}",
+                                                    path.points_refunded(),
+                                                    describe(&path.refunded),
+                                                )}
+                                            </div>
+                                        }
+                                    })}
+                                <button on:click=move |_| {
+                                    highlight.set(shown.clone())
+                                }>"Show path"</button>
+                                <button on:click=move |_| {
+                                    set_talent_str.set(string.clone())
+                                }>"Apply"</button>
+                            }
+                                .into_any()
+                        }
+                    })
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,159 @@
+use crate::talent_configuration::TalentConfiguration;
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::{
+    PointBudget, TraitTree, TraitTreeEntry, TraitTreeEntryType, TraitTreeNode, TraitTreeNodeType,
+};
+
+/*
+ * A small tree for unit tests, rows top down, budgets class 4, spec 2 and hero 2:
+
+class   1 Steady Aim          5 Long Reach (entry has 2 ranks, node none)
+        2 Quick Shot (2)      3 Split Path: Left Path | Right Path
+        4 Deep Focus (3 points above)
+spec    6 Sharp Eye
+        7 Keen Edge
+hero    9 Hero Choice: Ranger (50) | Warden (51)
+        10 Ranger Mark        11 Warden Guard
+        12 Ranger Volley      13 Warden Wall
+
+ * Entry ids are the node id times 10 plus the option, spell ids the entry id plus 1000.
+ */
+
+pub const SPEC_ID: usize = 268;
+
+fn entry(id: usize, name: &str, max_ranks: Option<usize>) -> TraitTreeEntry {
+    TraitTreeEntry {
+        id: Some(id),
+        definition_id: Some(id + 2000),
+        max_ranks,
+        node_type: Some(TraitTreeEntryType::Passive),
+        name: Some(name.to_string()),
+        spell_id: Some(id + 1000),
+        ..Default::default()
+    }
+}
+
+fn node(id: usize, name: &str, (pos_x, pos_y): (i32, i32), prev: &[usize]) -> TraitTreeNode {
+    TraitTreeNode {
+        id,
+        pos_x,
+        pos_y,
+        max_ranks: Some(1),
+        name: name.to_string(),
+        node_type: TraitTreeNodeType::Single,
+        entry_node: Some(prev.is_empty()),
+        prev: prev.to_vec(),
+        entries: vec![entry(id * 10, name, None)],
+        ..Default::default()
+    }
+}
+
+fn choice(
+    id: usize,
+    name: &str,
+    position: (i32, i32),
+    prev: &[usize],
+    options: &[&str],
+) -> TraitTreeNode {
+    TraitTreeNode {
+        node_type: TraitTreeNodeType::Choice,
+        entries: options
+            .iter()
+            .enumerate()
+            .map(|(option, name)| entry(id * 10 + option, name, Some(1)))
+            .collect(),
+        ..node(id, name, position, prev)
+    }
+}
+
+fn hero(id: usize, name: &str, position: (i32, i32), prev: &[usize], tree: usize) -> TraitTreeNode {
+    TraitTreeNode {
+        trait_sub_tree_id: Some(tree),
+        ..node(id, name, position, prev)
+    }
+}
+
+pub fn test_tree() -> TraitTree {
+    let long_reach = TraitTreeNode {
+        max_ranks: None,
+        entries: vec![entry(50, "Long Reach", Some(2))],
+        ..node(5, "Long Reach", (600, 0), &[])
+    };
+    let quick_shot = TraitTreeNode {
+        max_ranks: Some(2),
+        ..node(2, "Quick Shot", (0, 600), &[1])
+    };
+    let deep_focus = TraitTreeNode {
+        req_points: Some(3),
+        ..node(4, "Deep Focus", (0, 1200), &[2])
+    };
+    let mut hero_choice = choice(9, "Hero Choice", (0, 0), &[], &["Ranger", "Warden"]);
+    for (entry, tree) in hero_choice.entries.iter_mut().zip([50, 51]) {
+        entry.node_type = Some(TraitTreeEntryType::SubTree);
+        entry.trait_sub_tree_id = Some(tree);
+    }
+    hero_choice.node_type = TraitTreeNodeType::SubTree;
+
+    let mut trait_tree = TraitTree {
+        trait_tree_id: 1,
+        class_id: 3,
+        spec_id: SPEC_ID,
+        class_name: "Hunter".to_string(),
+        spec_name: "Marksmanship".to_string(),
+        class_nodes: vec![
+            node(1, "Steady Aim", (0, 0), &[]),
+            quick_shot,
+            choice(
+                3,
+                "Split Path",
+                (600, 600),
+                &[1],
+                &["Left Path", "Right Path"],
+            ),
+            deep_focus,
+            long_reach,
+        ],
+        spec_nodes: vec![
+            node(6, "Sharp Eye", (0, 0), &[]),
+            node(7, "Keen Edge", (0, 600), &[6]),
+        ],
+        hero_nodes: vec![
+            hero(10, "Ranger Mark", (0, 0), &[], 50),
+            hero(11, "Warden Guard", (600, 0), &[], 51),
+            hero(12, "Ranger Volley", (0, 600), &[10], 50),
+            hero(13, "Warden Wall", (600, 600), &[11], 51),
+        ],
+        sub_tree_nodes: vec![hero_choice],
+        full_node_order: vec![1, 2, 3, 4, 5, 6, 7, 9, 10, 11, 12, 13],
+        point_budget: PointBudget {
+            class: 4,
+            spec: 2,
+            hero: 2,
+        },
+        tree_hash: None,
+        index: Default::default(),
+    };
+    for prev in [1, 2, 6, 10, 11] {
+        let next = trait_tree
+            .nodes()
+            .filter(|node| node.prev.contains(&prev))
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+        for nodes in [
+            &mut trait_tree.class_nodes,
+            &mut trait_tree.spec_nodes,
+            &mut trait_tree.hero_nodes,
+        ] {
+            if let Some(node) = nodes.iter_mut().find(|node| node.id == prev) {
+                node.next = next.clone();
+            }
+        }
+    }
+    trait_tree.annotate_nodes();
+    trait_tree.build_index();
+    trait_tree
+}
+
+pub fn empty_configuration() -> TalentConfiguration {
+    TalentConfiguration::new(SPEC_ID, TalentEncoding::default(), vec![test_tree()]).unwrap()
+}
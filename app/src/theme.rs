@@ -0,0 +1,176 @@
+use leptos::prelude::*;
+use thaw::Theme;
+
+// colors are any svg paint value, fonts any css font-family
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeTheme {
+    pub selected: String,
+    pub partial: String,
+    pub unselected: String,
+    pub locked: String,
+    pub choice: String,
+    pub hero: String,
+    pub granted: String,
+    // search matches and paths
+    pub highlight: String,
+    // labels, counters and unlocked gates
+    pub text: String,
+    pub muted: String,
+    pub background: String,
+    pub focus: String,
+    pub node_stroke_width: f64,
+    pub edge_stroke_width: f64,
+    pub font_family: String,
+    // rank badges and gate labels
+    pub label_font_size: f64,
+    // section point counters
+    pub heading_font_size: f64,
+}
+
+impl TreeTheme {
+    pub fn light() -> Self {
+        Self {
+            selected: "green".to_string(),
+            partial: "yellowgreen".to_string(),
+            unselected: "goldenrod".to_string(),
+            locked: "red".to_string(),
+            choice: "darkorange".to_string(),
+            hero: "mediumpurple".to_string(),
+            granted: "teal".to_string(),
+            highlight: "magenta".to_string(),
+            text: "#242424".to_string(),
+            muted: "gray".to_string(),
+            background: "#ffffff".to_string(),
+            focus: "dodgerblue".to_string(),
+            node_stroke_width: 2.0,
+            edge_stroke_width: 1.0,
+            font_family: "sans-serif".to_string(),
+            label_font_size: 7.0,
+            heading_font_size: 9.0,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            selected: "#4caf50".to_string(),
+            partial: "#c0ca33".to_string(),
+            unselected: "#ffc107".to_string(),
+            locked: "#ef5350".to_string(),
+            choice: "#ff9800".to_string(),
+            hero: "#b39ddb".to_string(),
+            granted: "#4db6ac".to_string(),
+            highlight: "#f06292".to_string(),
+            text: "#e0e0e0".to_string(),
+            muted: "#9e9e9e".to_string(),
+            background: "#1f1f1f".to_string(),
+            focus: "#64b5f6".to_string(),
+            ..Self::light()
+        }
+    }
+
+    // Okabe-Ito palette, states also differ in lightness for monochrome vision
+    pub fn colorblind() -> Self {
+        Self {
+            selected: "#0072b2".to_string(),
+            partial: "#56b4e9".to_string(),
+            unselected: "#e69f00".to_string(),
+            locked: "#d55e00".to_string(),
+            choice: "#cc79a7".to_string(),
+            hero: "#009e73".to_string(),
+            granted: "#666666".to_string(),
+            highlight: "#000000".to_string(),
+            focus: "#000000".to_string(),
+            node_stroke_width: 2.5,
+            edge_stroke_width: 1.5,
+            ..Self::light()
+        }
+    }
+
+    // consumed by style/main.scss for everything around the svg
+    pub fn css_vars(&self) -> String {
+        format!(
+            "--tree-text: {}; --tree-background: {}; --tree-focus: {}; --tree-font-family: {};",
+            self.text, self.background, self.focus, self.font_family
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThemePreset {
+    #[default]
+    Light,
+    Dark,
+    Colorblind,
+}
+
+impl ThemePreset {
+    pub const ALL: [ThemePreset; 3] = [
+        ThemePreset::Light,
+        ThemePreset::Dark,
+        ThemePreset::Colorblind,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemePreset::Light => "light",
+            ThemePreset::Dark => "dark",
+            ThemePreset::Colorblind => "colorblind",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThemePreset::Light => "Light",
+            ThemePreset::Dark => "Dark",
+            ThemePreset::Colorblind => "Colorblind safe",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn tree_theme(&self) -> TreeTheme {
+        match self {
+            ThemePreset::Light => TreeTheme::light(),
+            ThemePreset::Dark => TreeTheme::dark(),
+            ThemePreset::Colorblind => TreeTheme::colorblind(),
+        }
+    }
+
+    // thaw has no colorblind theme, its widgets do not encode state in color alone
+    pub fn thaw_theme(&self) -> Theme {
+        match self {
+            ThemePreset::Dark => Theme::dark(),
+            _ => Theme::light(),
+        }
+    }
+}
+
+#[component]
+pub fn ThemeSwitcher(theme_preset: RwSignal<ThemePreset>) -> impl IntoView {
+    view! {
+        <label>
+            "Theme "
+            <select on:change:target=move |tag| {
+                if let Some(preset) = ThemePreset::from_name(&tag.target().value()) {
+                    theme_preset.set(preset);
+                }
+            }>
+                {ThemePreset::ALL
+                    .into_iter()
+                    .map(|preset| {
+                        view! {
+                            <option
+                                value=preset.name()
+                                selected=move || theme_preset.get() == preset
+                            >
+                                {preset.label()}
+                            </option>
+                        }
+                    })
+                    .collect::<Vec<_>>()}
+            </select>
+        </label>
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 /*
@@ -12,6 +14,7 @@ struct TraitTree {
   spec_id,
   class_name,
   spec_name,
+  tree_hash?, // 16 bytes as written by the game, strings then carry zeros without it
   nodes, // ordered by node_order
 }
 
@@ -42,7 +45,7 @@ struct TraitEntry {
 
  */
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct TraitTree {
     pub trait_tree_id: usize,
@@ -55,9 +58,43 @@ pub struct TraitTree {
     pub hero_nodes: Vec<TraitTreeNode>,
     pub sub_tree_nodes: Vec<TraitTreeNode>,
     pub full_node_order: Vec<usize>,
+    #[serde(default)]
+    pub point_budget: PointBudget,
+    // the game's hash of the tree, none until the data carries `treeHash`, which leaves hash
+    // validation off
+    #[serde(default)]
+    pub tree_hash: Option<Vec<u8>>,
+    // built by `build_index` after loading, never part of the data
+    #[serde(skip)]
+    pub index: TraitTreeIndex,
+}
+
+// positions are into class, spec, hero and sub tree nodes chained in that order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraitTreeIndex {
+    nodes: HashMap<usize, usize>,
+    // lowercase node name to every node with it
+    names: HashMap<String, Vec<usize>>,
+    // spell and definition ids to node and entry position
+    spells: HashMap<usize, Vec<(usize, usize)>>,
+    definitions: HashMap<usize, (usize, usize)>,
+}
+
+impl TraitTreeIndex {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
 }
 
+// points available in each tree at max level
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PointBudget {
+    pub class: usize,
+    pub spec: usize,
+    pub hero: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct TraitTreeEntry {
     pub id: Option<usize>,
@@ -73,9 +110,27 @@ pub struct TraitTreeEntry {
     pub spell_id: Option<usize>,
     pub index: Option<usize>,
     pub trait_sub_tree_id: Option<usize>,
+    // spell text with `{0}`, `{1}`, ... placeholders filled from `rank_values`
+    pub description: Option<String>,
+    // per placeholder, one value per rank
+    #[serde(default)]
+    pub rank_values: Vec<Vec<f64>>,
+    // seconds
+    pub cooldown: Option<f64>,
+    // seconds, zero is instant
+    pub cast_time: Option<f64>,
+    // yards
+    pub range: Option<f64>,
+    pub resource_cost: Option<ResourceCost>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResourceCost {
+    pub amount: f64,
+    pub resource: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct TraitTreeNode {
     pub id: usize,
@@ -95,6 +150,193 @@ pub struct TraitTreeNode {
     #[serde(alias = "subTreeId")]
     pub trait_sub_tree_id: Option<usize>,
     pub req_points: Option<usize>,
+    // granted without spending points, e.g. by the spec or hero tree
+    pub free_node: Option<bool>,
+    pub node_source: Option<NodeSource>,
+    // row within the node's class, spec or hero tree, counted from 0 at the top
+    pub node_tier: Option<usize>,
+    // stands in for `node_tier` when the data has none, see `annotate_nodes`
+    #[serde(skip)]
+    pub inferred_tier: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeSource {
+    Class,
+    Spec,
+    Hero,
+}
+
+impl TraitTreeNode {
+    pub fn is_free(&self) -> bool {
+        self.free_node.unwrap_or(false)
+    }
+
+    pub fn tier(&self) -> usize {
+        self.node_tier.or(self.inferred_tier).unwrap_or(0)
+    }
+}
+
+impl TraitTree {
+    // the talent data does not carry source and tier yet and there is no converter to add them,
+    // so they are filled in here after fetching. Inferred tiers are the distinct pos_y values of
+    // each class, spec or hero tree in order, good for grouping rows and gates but not for
+    // placing them, layout keeps using pos_y for those nodes
+    pub fn annotate_nodes(&mut self) {
+        fn annotate(nodes: &mut [TraitTreeNode], source: NodeSource) {
+            let mut groups: HashMap<Option<usize>, Vec<i32>> = HashMap::new();
+            for node in nodes.iter() {
+                groups
+                    .entry(node.trait_sub_tree_id)
+                    .or_default()
+                    .push(node.pos_y);
+            }
+            for rows in groups.values_mut() {
+                rows.sort();
+                rows.dedup();
+            }
+            for node in nodes.iter_mut() {
+                node.node_source.get_or_insert(source);
+                if node.node_tier.is_none() {
+                    node.inferred_tier = groups[&node.trait_sub_tree_id]
+                        .binary_search(&node.pos_y)
+                        .ok();
+                }
+            }
+        }
+
+        annotate(&mut self.class_nodes, NodeSource::Class);
+        annotate(&mut self.spec_nodes, NodeSource::Spec);
+        annotate(&mut self.hero_nodes, NodeSource::Hero);
+        annotate(&mut self.sub_tree_nodes, NodeSource::Hero);
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &TraitTreeNode> {
+        self.class_nodes
+            .iter()
+            .chain(self.spec_nodes.iter())
+            .chain(self.hero_nodes.iter())
+            .chain(self.sub_tree_nodes.iter())
+    }
+
+    fn node_at(&self, mut position: usize) -> Option<&TraitTreeNode> {
+        for nodes in [
+            &self.class_nodes,
+            &self.spec_nodes,
+            &self.hero_nodes,
+            &self.sub_tree_nodes,
+        ] {
+            match nodes.get(position) {
+                Some(node) => return Some(node),
+                None => position -= nodes.len(),
+            }
+        }
+        None
+    }
+
+    fn entry_at(&self, (node, entry): (usize, usize)) -> Option<(&TraitTreeNode, &TraitTreeEntry)> {
+        let node = self.node_at(node)?;
+        Some((node, node.entries.get(entry)?))
+    }
+
+    // the first node or entry wins when the data repeats an id
+    pub fn build_index(&mut self) {
+        let mut index = TraitTreeIndex::default();
+        for (position, node) in self.nodes().enumerate() {
+            index.nodes.entry(node.id).or_insert(position);
+            index
+                .names
+                .entry(node.name.to_lowercase())
+                .or_default()
+                .push(position);
+            for (entry_position, entry) in node.entries.iter().enumerate() {
+                if let Some(spell_id) = entry.spell_id {
+                    index
+                        .spells
+                        .entry(spell_id)
+                        .or_default()
+                        .push((position, entry_position));
+                }
+                if let Some(definition_id) = entry.definition_id {
+                    index
+                        .definitions
+                        .entry(definition_id)
+                        .or_insert((position, entry_position));
+                }
+            }
+        }
+        self.index = index;
+    }
+
+    pub fn node(&self, id: usize) -> Option<&TraitTreeNode> {
+        self.node_at(*self.index.nodes.get(&id)?)
+    }
+
+    // case insensitive
+    pub fn nodes_named(&self, name: &str) -> Vec<&TraitTreeNode> {
+        self.index
+            .names
+            .get(&name.to_lowercase())
+            .map_or_else(Vec::new, |positions| {
+                positions
+                    .iter()
+                    .filter_map(|position| self.node_at(*position))
+                    .collect()
+            })
+    }
+
+    pub fn entries_with_spell(&self, spell_id: usize) -> Vec<(&TraitTreeNode, &TraitTreeEntry)> {
+        self.index
+            .spells
+            .get(&spell_id)
+            .map_or_else(Vec::new, |positions| {
+                positions
+                    .iter()
+                    .filter_map(|position| self.entry_at(*position))
+                    .collect()
+            })
+    }
+
+    pub fn entry_by_definition(
+        &self,
+        definition_id: usize,
+    ) -> Option<(&TraitTreeNode, &TraitTreeEntry)> {
+        self.entry_at(*self.index.definitions.get(&definition_id)?)
+    }
+}
+
+impl TraitTreeEntry {
+    // ranks past the last known value reuse the last value
+    pub fn description_at_rank(&self, rank: usize) -> Option<String> {
+        let mut description = self.description.clone()?;
+        for (index, values) in self.rank_values.iter().enumerate() {
+            let Some(value) = values.get(rank.max(1) - 1).or(values.last()) else {
+                continue;
+            };
+            description = description.replace(&format!("{{{index}}}"), &value.to_string());
+        }
+        Some(description)
+    }
+
+    pub fn spell_details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(cost) = &self.resource_cost {
+            details.push(format!("{} {}", cost.amount, cost.resource));
+        }
+        if let Some(range) = self.range {
+            details.push(format!("{range} yd range"));
+        }
+        match self.cast_time {
+            Some(cast_time) if cast_time > 0.0 => details.push(format!("{cast_time} sec cast")),
+            Some(_) => details.push("Instant".to_string()),
+            None => (),
+        }
+        if let Some(cooldown) = self.cooldown {
+            details.push(format!("{cooldown} sec cooldown"));
+        }
+        details
+    }
 }
 
 impl Default for TraitTreeEntry {
@@ -108,6 +350,12 @@ impl Default for TraitTreeEntry {
             spell_id: None,
             index: None,
             trait_sub_tree_id: None,
+            description: None,
+            rank_values: Default::default(),
+            cooldown: None,
+            cast_time: None,
+            range: None,
+            resource_cost: None,
         }
     }
 }
@@ -127,6 +375,10 @@ impl Default for TraitTreeNode {
             entries: Default::default(),
             trait_sub_tree_id: Default::default(),
             req_points: None,
+            free_node: None,
+            node_source: None,
+            node_tier: None,
+            inferred_tier: None,
         }
     }
 }
@@ -189,12 +441,25 @@ mod trait_tree_node_type_deserializer {
     }
 }
 
+// fetched once by `HomePage` and shared through context
+pub type TraitTreeData = LocalResource<Result<Vec<TraitTree>, Error>>;
+
+pub fn use_trait_tree_data() -> TraitTreeData {
+    use_context::<TraitTreeData>()
+        .expect("Must have a parent that provides a `TraitTreeData` context.")
+}
+
 pub async fn fetch_trait_trees() -> Result<Vec<TraitTree>, Error> {
-    Ok(reqwasm::http::Request::get("/talent-data/talents.json")
+    let mut trait_trees: Vec<TraitTree> = reqwasm::http::Request::get("/talent-data/talents.json")
         .send()
         .await?
         .json()
-        .await?)
+        .await?;
+    for trait_tree in trait_trees.iter_mut() {
+        trait_tree.annotate_nodes();
+        trait_tree.build_index();
+    }
+    Ok(trait_trees)
 }
 
 #[component]
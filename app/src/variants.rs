@@ -0,0 +1,512 @@
+use std::collections::{HashMap, HashSet};
+
+use leptos::prelude::*;
+
+use crate::configuration_generation::talent_name;
+use crate::layout::LayoutSection;
+use crate::talent_configuration::{NodeAction, TalentConfiguration};
+use crate::talent_encoding::TalentEncoding;
+use crate::trait_tree::{use_trait_tree_data, TraitTreeNode, TraitTreeNodeType};
+
+/*
+ * Builds a few moves away from a build, for simming every alternative to it. A move is one of:
+ * - one point refunded from a talent and spent on another
+ * - a taken choice node switched to another option
+ * - the other hero tree, with its points spent top down
+ * Only legal builds are kept, and each build is listed once with the fewest moves reaching it.
+ */
+
+const MAX_VARIANTS: usize = 2000;
+const VARIANTS_SHOWN: usize = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildVariant {
+    pub configuration: TalentConfiguration,
+    // one description per move, in order
+    pub changes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VariantOutcome {
+    pub variants: Vec<BuildVariant>,
+    // false if the list reached MAX_VARIANTS, more variants may exist
+    pub complete: bool,
+}
+
+impl TalentConfiguration {
+    fn into_variant(mut self, change: String, config: &TalentEncoding) -> BuildVariant {
+        self.string = self.encode(config);
+        BuildVariant {
+            configuration: self,
+            changes: vec![change],
+        }
+    }
+
+    // `(from, to, option)` for every legal move of one point, `option` picks the entry of an
+    // untaken choice node. Only the nodes a move touches are checked, the points receiving talent,
+    // the children of the refunded one and taken talents behind a gate, so the build has to be
+    // legal to begin with.
+    fn legal_point_moves(&self) -> Vec<(usize, usize, Option<usize>)> {
+        if !self.is_legal() {
+            return Vec::new();
+        }
+        let indices = (0..self.all_talents.len())
+            .filter(|index| {
+                let node = &self.all_talents[*index].trait_tree_node;
+                node.id != 0
+                    && node.node_type != TraitTreeNodeType::SubTree
+                    && !node.is_free()
+                    && node
+                        .trait_sub_tree_id
+                        .is_none_or(|id| self.subtrees.contains(&id))
+            })
+            .collect::<Vec<_>>();
+        let section = |index: usize| self.layout_section(&self.all_talents[index].trait_tree_node);
+        let tier = |index: usize| self.all_talents[index].trait_tree_node.tier();
+        let spent = indices
+            .iter()
+            .map(|index| section(*index))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|section| (section, self.points_spent(section)))
+            .collect::<HashMap<_, _>>();
+        let spent_before = indices
+            .iter()
+            .map(|index| {
+                let node = &self.all_talents[*index].trait_tree_node;
+                (*index, self.points_spent_before(node))
+            })
+            .collect::<HashMap<_, _>>();
+        let gated = indices
+            .iter()
+            .copied()
+            .filter(|index| {
+                let talent = &self.all_talents[*index];
+                talent.rank > 0 && talent.trait_tree_node.req_points.is_some_and(|req| req > 0)
+            })
+            .collect::<Vec<_>>();
+
+        let mut moves = Vec::new();
+        for &from in indices
+            .iter()
+            .filter(|index| self.all_talents[**index].rank > 0)
+        {
+            let refunded = &self.all_talents[from];
+            for &to in indices.iter().filter(|index| **index != from) {
+                let talent = &self.all_talents[to];
+                if talent.rank > 0 && talent.is_maxed() {
+                    continue;
+                }
+                let node = &talent.trait_tree_node;
+                if section(from) != section(to)
+                    && spent[&section(to)] >= self.point_budget(section(to))
+                {
+                    continue;
+                }
+                // points on rows above the node after the move
+                let points_before = |index: usize| {
+                    let gains = section(to) == section(index) && tier(to) < tier(index);
+                    let loses = section(from) == section(index) && tier(from) < tier(index);
+                    spent_before[&index] + usize::from(gains) - usize::from(loses)
+                };
+                let gate_holds = |index: usize| {
+                    let req_points = self.all_talents[index].trait_tree_node.req_points;
+                    req_points.is_none_or(|req_points| points_before(index) >= req_points)
+                };
+
+                // an untaken choice node can be taken as any of its options
+                let options = match (&node.node_type, talent.rank) {
+                    (TraitTreeNodeType::Choice, 0) => (0..node.entries.len()).map(Some).collect(),
+                    _ => vec![None],
+                };
+                for option in options {
+                    let max_ranks = match option {
+                        Some(option) => node.entries[option]
+                            .max_ranks
+                            .or(node.max_ranks)
+                            .unwrap_or(1),
+                        None => talent.max_ranks(),
+                    };
+                    let maxed_after = |node_id: &usize| {
+                        self.talent_index.get(node_id).is_some_and(|&index| {
+                            let te = &self.all_talents[index];
+                            match index {
+                                // never above its maximum, so one point less is never maxed
+                                _ if index == from => false,
+                                _ if index == to => te.rank + 1 >= max_ranks,
+                                _ => te.is_maxed(),
+                            }
+                        })
+                    };
+                    let connected = |node: &TraitTreeNode| {
+                        node.entry_node.unwrap_or(false)
+                            || node.prev.is_empty()
+                            || node.prev.iter().any(maxed_after)
+                    };
+
+                    let children_hold = !refunded.is_maxed()
+                        || refunded
+                            .trait_tree_node
+                            .next
+                            .iter()
+                            .filter_map(|id| self.talent(*id))
+                            .filter(|te| te.rank > 0 && !te.trait_tree_node.is_free())
+                            .all(|te| connected(&te.trait_tree_node));
+                    let gates_hold = gated
+                        .iter()
+                        .filter(|index| **index != from || refunded.rank > 1)
+                        .all(|index| gate_holds(*index));
+                    if connected(node) && gate_holds(to) && children_hold && gates_hold {
+                        moves.push((from, to, option));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    // builds are only made when asked for, the caller stops once it has enough
+    fn point_moves<'a>(
+        &'a self,
+        config: &'a TalentEncoding,
+    ) -> impl Iterator<Item = BuildVariant> + 'a {
+        self.legal_point_moves()
+            .into_iter()
+            .map(move |(from, to, option)| {
+                let mut moved = self.clone();
+                moved.all_talents[from].rank -= 1;
+                let talent = &mut moved.all_talents[to];
+                if let Some(option) = option {
+                    talent.trait_tree_entry = talent.trait_tree_node.entries[option].clone();
+                }
+                talent.rank += 1;
+                moved.refresh_selection();
+                let change = format!(
+                    "{} to {}",
+                    talent_name(&self.all_talents[from]),
+                    talent_name(&moved.all_talents[to])
+                );
+                moved.into_variant(change, config)
+            })
+    }
+
+    fn choice_flips<'a>(
+        &'a self,
+        config: &'a TalentEncoding,
+    ) -> impl Iterator<Item = BuildVariant> + 'a {
+        self.all_talents
+            .iter()
+            .enumerate()
+            .filter(|(_, talent)| {
+                talent.trait_tree_node.node_type == TraitTreeNodeType::Choice && talent.rank > 0
+            })
+            .flat_map(move |(index, talent)| {
+                talent
+                    .trait_tree_node
+                    .entries
+                    .iter()
+                    .filter(move |tte| tte.id != talent.trait_tree_entry.id)
+                    .filter_map(move |entry| {
+                        let mut flipped = self.clone();
+                        let target = &mut flipped.all_talents[index];
+                        target.trait_tree_entry = entry.clone();
+                        target.rank = target.rank.min(target.max_ranks());
+                        flipped.refresh_selection();
+                        if !flipped.is_legal() {
+                            return None;
+                        }
+                        let change = format!(
+                            "{} to {}",
+                            talent_name(talent),
+                            talent_name(&flipped.all_talents[index])
+                        );
+                        Some(flipped.into_variant(change, config))
+                    })
+            })
+    }
+
+    fn hero_tree_switches<'a>(
+        &'a self,
+        config: &'a TalentEncoding,
+    ) -> impl Iterator<Item = BuildVariant> + 'a {
+        let hero_trees = self.hero_trees();
+        let points = self
+            .subtrees
+            .iter()
+            .map(|id| self.points_spent(LayoutSection::Hero(*id)))
+            .sum::<usize>();
+        let from = match self.subtrees.as_slice() {
+            [id] => hero_trees
+                .iter()
+                .find(|(hero_tree, _)| hero_tree == id)
+                .map_or_else(|| id.to_string(), |(_, name)| name.clone()),
+            _ => "No hero tree".to_string(),
+        };
+
+        hero_trees
+            .into_iter()
+            .filter(|(id, _)| !self.subtrees.contains(id))
+            .map(move |(id, name)| {
+                let mut switched = self.clone();
+                switched.select_hero_tree(id, config);
+                // the same number of points, top row first
+                while switched.points_spent(LayoutSection::Hero(id)) < points {
+                    let next = switched
+                        .all_talents
+                        .iter()
+                        .filter(|te| te.trait_tree_node.trait_sub_tree_id == Some(id))
+                        .filter(|te| switched.can_allocate(te))
+                        .min_by_key(|te| (te.trait_tree_node.tier(), te.trait_tree_node.pos_x))
+                        .map(|te| te.trait_tree_node.id);
+                    let allocated = next.is_some_and(|node_id| {
+                        switched.apply(node_id, NodeAction::Allocate, config)
+                    });
+                    if !allocated {
+                        break;
+                    }
+                }
+                let change = format!("Hero tree {from} to {name}");
+                switched.into_variant(change, config)
+            })
+    }
+
+    // every legal build at most `moves` moves away, closest first
+    pub fn variants(&self, moves: usize, config: &TalentEncoding) -> VariantOutcome {
+        let mut seen = HashSet::from([self.string.clone(), self.encode(config)]);
+        let mut variants = Vec::new();
+        let mut frontier = vec![BuildVariant {
+            configuration: self.clone(),
+            changes: Vec::new(),
+        }];
+
+        for _ in 0..moves {
+            let mut next = Vec::new();
+            for variant in &frontier {
+                let build = &variant.configuration;
+                let mut steps = build
+                    .point_moves(config)
+                    .chain(build.choice_flips(config))
+                    .chain(build.hero_tree_switches(config));
+                while variants.len() < MAX_VARIANTS {
+                    let Some(step) = steps.next() else {
+                        break;
+                    };
+                    if !seen.insert(step.configuration.string.clone()) {
+                        continue;
+                    }
+                    let reached = BuildVariant {
+                        configuration: step.configuration,
+                        changes: [variant.changes.clone(), step.changes].concat(),
+                    };
+                    next.push(reached.clone());
+                    variants.push(reached);
+                }
+                if variants.len() >= MAX_VARIANTS {
+                    return VariantOutcome {
+                        variants,
+                        complete: false,
+                    };
+                }
+            }
+            frontier = next;
+        }
+
+        VariantOutcome {
+            variants,
+            complete: true,
+        }
+    }
+}
+
+#[component]
+pub fn BuildVariantsView(
+    talent_encoding: ReadSignal<TalentEncoding>,
+    talent_str: ReadSignal<String>,
+    set_talent_str: WriteSignal<String>,
+) -> impl IntoView {
+    let trait_tree_data = use_trait_tree_data();
+    let (moves, set_moves) = signal(1usize);
+    let (outcome, set_outcome) = signal(None::<Result<VariantOutcome, String>>);
+
+    let generate = move |_| {
+        let Some(Ok(trait_trees)) = trait_tree_data.get() else {
+            set_outcome.set(Some(Err("Talent data is not loaded yet".to_string())));
+            return;
+        };
+        let config = talent_encoding.get();
+        let result =
+            TalentConfiguration::new_from_str(&talent_str.get(), config.clone(), trait_trees)
+                .map(|configuration| configuration.variants(moves.get(), &config))
+                .map_err(|err| err.to_string());
+        set_outcome.set(Some(result));
+    };
+
+    let strings = move || {
+        outcome.with(|outcome| match outcome {
+            Some(Ok(outcome)) => outcome
+                .variants
+                .iter()
+                .map(|variant| variant.configuration.string.clone())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        })
+    };
+
+    view! {
+        <div class="build-variants">
+            <label>
+                "Moves "
+                <input
+                    type="number"
+                    min="1"
+                    max="3"
+                    prop:value=move || moves.get().to_string()
+                    on:change:target=move |tag| {
+                        if let Ok(count) = tag.target().value().parse::<usize>() {
+                            set_moves.set(count.clamp(1, 3));
+                        }
+                    }
+                />
+            </label>
+            <button on:click=generate>"Generate variants"</button>
+            {move || {
+                outcome
+                    .get()
+                    .map(|result| match result {
+                        Err(err) => view! { <div>{err}</div> }.into_any(),
+                        Ok(outcome) => {
+                            let count = match outcome.complete {
+                                true => format!("{} variants", outcome.variants.len()),
+                                false => format!("First {} variants", outcome.variants.len()),
+                            };
+                            let hidden = outcome.variants.len().saturating_sub(VARIANTS_SHOWN);
+                            view! {
+                                <div>{count}</div>
+                                <textarea readonly=true prop:value=strings />
+                                <ol>
+                                    {outcome
+                                        .variants
+                                        .into_iter()
+                                        .take(VARIANTS_SHOWN)
+                                        .map(|variant| {
+                                            let string = variant.configuration.string;
+                                            view! {
+                                                <li>
+                                                    <button on:click=move |_| {
+                                                        set_talent_str.set(string.clone())
+                                                    }>"Load"</button>
+                                                    {format!(" {}", variant.changes.join(", then "))}
+                                                </li>
+                                            }
+                                        })
+                                        .collect::<Vec<_>>()}
+                                </ol>
+                                {(hidden > 0).then(|| view! { <div>{format!("and {hidden} more")}</div> })}
+                            }
+                                .into_any()
+                        }
+                    })
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tree::empty_configuration;
+
+    // every move made and checked in full, what `legal_point_moves` has to agree with
+    fn brute_force_moves(
+        configuration: &TalentConfiguration,
+    ) -> HashSet<(usize, usize, Option<usize>)> {
+        let mut moves = HashSet::new();
+        for from in 0..configuration.all_talents.len() {
+            for to in 0..configuration.all_talents.len() {
+                let talent = &configuration.all_talents[to];
+                let node = &talent.trait_tree_node;
+                if from == to
+                    || configuration.all_talents[from].rank == 0
+                    || configuration.all_talents[from].trait_tree_node.is_free()
+                    || configuration.all_talents[from].trait_tree_node.node_type
+                        == TraitTreeNodeType::SubTree
+                    || node.is_free()
+                    || node.node_type == TraitTreeNodeType::SubTree
+                    || node
+                        .trait_sub_tree_id
+                        .is_some_and(|id| !configuration.subtrees.contains(&id))
+                {
+                    continue;
+                }
+                let options = match (&node.node_type, talent.rank) {
+                    (TraitTreeNodeType::Choice, 0) => (0..node.entries.len()).map(Some).collect(),
+                    _ => vec![None],
+                };
+                for option in options {
+                    let mut moved = configuration.clone();
+                    moved.all_talents[from].rank -= 1;
+                    if let Some(option) = option {
+                        moved.all_talents[to].trait_tree_entry = node.entries[option].clone();
+                    }
+                    moved.all_talents[to].rank += 1;
+                    moved.refresh_selection();
+                    if moved.is_legal() {
+                        moves.insert((from, to, option));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    fn allocated(node_ids: &[usize], hero_tree: usize) -> TalentConfiguration {
+        let config = TalentEncoding::default();
+        let mut configuration = empty_configuration();
+        configuration.select_hero_tree(hero_tree, &config);
+        for node_id in node_ids {
+            assert!(configuration.apply(*node_id, NodeAction::Allocate, &config));
+        }
+        configuration
+    }
+
+    #[test]
+    fn point_moves_match_full_checks() {
+        for (node_ids, hero_tree) in [
+            (&[][..], 50),
+            (&[1, 2, 2, 4, 6, 10][..], 50),
+            (&[1, 3, 5, 5, 6, 7, 11, 13][..], 51),
+            (&[5, 1, 2, 6, 11][..], 51),
+        ] {
+            let configuration = allocated(node_ids, hero_tree);
+            let moves = configuration.legal_point_moves();
+            assert_eq!(
+                moves.iter().copied().collect::<HashSet<_>>(),
+                brute_force_moves(&configuration),
+                "{node_ids:?}"
+            );
+            assert!(configuration
+                .point_moves(&TalentEncoding::default())
+                .all(|variant| variant.configuration.is_legal()));
+        }
+    }
+
+    #[test]
+    fn variants_are_unique_and_legal() {
+        let configuration = allocated(&[1, 2, 2, 6, 10], 50);
+        let outcome = configuration.variants(2, &TalentEncoding::default());
+        assert!(outcome.complete);
+        let strings = outcome
+            .variants
+            .iter()
+            .map(|variant| variant.configuration.string.clone())
+            .collect::<HashSet<_>>();
+        assert_eq!(strings.len(), outcome.variants.len());
+        assert!(!strings.contains(&configuration.string));
+        assert!(outcome
+            .variants
+            .iter()
+            .all(|variant| variant.configuration.is_legal()
+                && (1..=2).contains(&variant.changes.len())));
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use leptos::prelude::*;
+use leptos::wasm_bindgen::JsValue;
+use leptos::web_sys::File;
+use serde::Deserialize;
+use thiserror::Error;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::constraints::NodeTarget;
+use crate::solver::Weights;
+use crate::trait_tree::{TraitTree, TraitTreeEntry, TraitTreeNode, TraitTreeNodeType};
+
+/*
+ * Talent weights from sim results, usually the dps gained by each talent. A single value applies
+ * to every rank, several are one value per rank. Keys are node ids, entry ids, spell ids,
+ * definition ids or names, a bare number is tried as a node id, then an entry id, then a spell id
+ * unless prefixed.
+ * CSV, the first line is skipped if it is a header:
+
+key,value
+"Node A",1520.5
+spell:12345,300,610
+entry:67890,42
+definition:24680,75
+
+ * JSON, either an object of keys or an array of records:
+
+{ "Node A": 1520.5, "spell:12345": [300, 610] }
+[
+  { "name": "Node A", "value": 1520.5 },
+  { "spell_id": 12345, "values": [300, 610] },
+  { "entry_id": 67890, "value": 42 }
+]
+
+ */
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WeightImportError {
+    #[error("Line {line}: {message}")]
+    Csv { line: usize, message: String },
+    #[error("Invalid weight JSON: {0}")]
+    Json(String),
+    #[error("The file contains no weights")]
+    Empty,
+    #[error("Could not read the file: {0}")]
+    Read(String),
+}
+
+impl From<JsValue> for WeightImportError {
+    fn from(value: JsValue) -> Self {
+        WeightImportError::Read(format!("{value:?}"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WeightKey {
+    Node(usize),
+    Entry(usize),
+    Spell(usize),
+    Definition(usize),
+    // node, entry or spell id
+    Id(usize),
+    Name(String),
+}
+
+impl fmt::Display for WeightKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeightKey::Node(id) => write!(f, "node:{id}"),
+            WeightKey::Entry(id) => write!(f, "entry:{id}"),
+            WeightKey::Spell(id) => write!(f, "spell:{id}"),
+            WeightKey::Definition(id) => write!(f, "definition:{id}"),
+            WeightKey::Id(id) => write!(f, "{id}"),
+            WeightKey::Name(name) => write!(f, "\"{name}\""),
+        }
+    }
+}
+
+impl WeightKey {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let prefixed = text.split_once(':').and_then(|(prefix, id)| {
+            let id = id.trim().parse::<usize>().ok()?;
+            match prefix.trim().to_lowercase().as_str() {
+                "node" => Some(WeightKey::Node(id)),
+                "entry" => Some(WeightKey::Entry(id)),
+                "spell" => Some(WeightKey::Spell(id)),
+                "definition" => Some(WeightKey::Definition(id)),
+                _ => None,
+            }
+        });
+        match (prefixed, text.parse::<usize>()) {
+            (Some(key), _) => key,
+            (None, Ok(id)) => WeightKey::Id(id),
+            (None, Err(_)) => WeightKey::Name(text.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonValues {
+    One(f64),
+    PerRank(Vec<f64>),
+}
+
+impl From<JsonValues> for Vec<f64> {
+    fn from(values: JsonValues) -> Self {
+        match values {
+            JsonValues::One(value) => vec![value],
+            JsonValues::PerRank(values) => values,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRecord {
+    node_id: Option<usize>,
+    entry_id: Option<usize>,
+    spell_id: Option<usize>,
+    definition_id: Option<usize>,
+    name: Option<String>,
+    #[serde(alias = "values")]
+    value: JsonValues,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonWeights {
+    Keys(BTreeMap<String, JsonValues>),
+    Records(Vec<JsonRecord>),
+}
+
+// splits one csv line, double quotes group commas and "" is a literal quote
+fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(String::new()),
+            (c, _) => fields.last_mut().unwrap().push(c),
+        }
+    }
+    match quoted {
+        true => Err("unterminated quote".to_string()),
+        false => Ok(fields),
+    }
+}
+
+fn parse_csv(text: &str) -> Result<Vec<(WeightKey, Vec<f64>)>, WeightImportError> {
+    let mut weights = Vec::new();
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    for (position, (line, text)) in lines.enumerate() {
+        let error = |message: String| WeightImportError::Csv { line, message };
+        let fields = csv_fields(text).map_err(error)?;
+        let values = fields[1..]
+            .iter()
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| format!("{value} is not a number"))
+            })
+            .collect::<Result<Vec<_>, _>>();
+        match values {
+            Ok(values) if values.is_empty() => {
+                return Err(error(format!("no value for {}", fields[0].trim())))
+            }
+            Ok(values) => weights.push((WeightKey::parse(&fields[0]), values)),
+            // the first line may name the columns
+            Err(_) if position == 0 => continue,
+            Err(message) => return Err(error(message)),
+        }
+    }
+    Ok(weights)
+}
+
+fn parse_json(text: &str) -> Result<Vec<(WeightKey, Vec<f64>)>, WeightImportError> {
+    let json = serde_json::from_str::<JsonWeights>(text)
+        .map_err(|err| WeightImportError::Json(err.to_string()))?;
+    match json {
+        JsonWeights::Keys(keys) => Ok(keys
+            .into_iter()
+            .map(|(key, values)| (WeightKey::parse(&key), values.into()))
+            .collect()),
+        JsonWeights::Records(records) => records
+            .into_iter()
+            .map(|record| {
+                let key = match (
+                    record.node_id,
+                    record.entry_id,
+                    record.spell_id,
+                    record.definition_id,
+                    record.name,
+                ) {
+                    (Some(id), ..) => WeightKey::Node(id),
+                    (None, Some(id), ..) => WeightKey::Entry(id),
+                    (None, None, Some(id), ..) => WeightKey::Spell(id),
+                    (None, None, None, Some(id), _) => WeightKey::Definition(id),
+                    (None, None, None, None, Some(name)) => WeightKey::Name(name),
+                    (None, None, None, None, None) => {
+                        return Err(WeightImportError::Json(
+                            "record without node_id, entry_id, spell_id, definition_id or name"
+                                .to_string(),
+                        ))
+                    }
+                };
+                Ok((key, record.value.into()))
+            })
+            .collect(),
+    }
+}
+
+pub fn parse_weights(text: &str) -> Result<Vec<(WeightKey, Vec<f64>)>, WeightImportError> {
+    let weights = match text.trim_start().starts_with(['{', '[']) {
+        true => parse_json(text)?,
+        false => parse_csv(text)?,
+    };
+    match weights.is_empty() {
+        true => Err(WeightImportError::Empty),
+        false => Ok(weights),
+    }
+}
+
+fn weighable(node: &&TraitTreeNode) -> bool {
+    node.node_type != TraitTreeNodeType::SubTree
+}
+
+fn node_target(node: &TraitTreeNode) -> NodeTarget {
+    NodeTarget {
+        node_id: node.id,
+        entry_id: None,
+    }
+}
+
+// narrowed to the entry on choice nodes
+fn entry_target(node: &TraitTreeNode, entry: &TraitTreeEntry) -> NodeTarget {
+    NodeTarget {
+        node_id: node.id,
+        entry_id: match node.node_type {
+            TraitTreeNodeType::Choice => entry.id,
+            _ => None,
+        },
+    }
+}
+
+// talents whose entry matches
+fn entry_targets(tree: &TraitTree, matches: impl Fn(&TraitTreeEntry) -> bool) -> Vec<NodeTarget> {
+    tree.nodes()
+        .filter(weighable)
+        .flat_map(|node| {
+            node.entries
+                .iter()
+                .filter(|entry| matches(entry))
+                .map(move |entry| entry_target(node, entry))
+        })
+        .collect()
+}
+
+// every talent the key could mean
+pub fn resolve_key(key: &WeightKey, tree: &TraitTree) -> Vec<NodeTarget> {
+    let mut targets = match key {
+        WeightKey::Node(id) => tree
+            .node(*id)
+            .filter(weighable)
+            .map(node_target)
+            .into_iter()
+            .collect(),
+        WeightKey::Entry(id) => entry_targets(tree, |entry| entry.id == Some(*id)),
+        WeightKey::Spell(id) => tree
+            .entries_with_spell(*id)
+            .into_iter()
+            .filter(|(node, _)| weighable(node))
+            .map(|(node, entry)| entry_target(node, entry))
+            .collect(),
+        WeightKey::Definition(id) => tree
+            .entry_by_definition(*id)
+            .filter(|(node, _)| weighable(node))
+            .map(|(node, entry)| entry_target(node, entry))
+            .into_iter()
+            .collect(),
+        WeightKey::Id(id) => [
+            WeightKey::Node(*id),
+            WeightKey::Entry(*id),
+            WeightKey::Spell(*id),
+        ]
+        .iter()
+        .map(|key| resolve_key(key, tree))
+        .find(|targets| !targets.is_empty())
+        .unwrap_or_default(),
+        WeightKey::Name(name) => {
+            let by_node = tree
+                .nodes_named(name)
+                .into_iter()
+                .filter(weighable)
+                .map(node_target)
+                .collect::<Vec<_>>();
+            match by_node.is_empty() {
+                false => by_node,
+                true => entry_targets(tree, |entry| {
+                    entry
+                        .name
+                        .as_ref()
+                        .is_some_and(|entry| entry.eq_ignore_ascii_case(name))
+                }),
+            }
+        }
+    };
+    // several entries of a node that is not a choice are the same target
+    targets.dedup();
+    targets
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeightImport {
+    pub weights: Weights,
+    pub matched: usize,
+    pub unmatched: Vec<WeightKey>,
+    // keys naming several talents are skipped, they need an id instead
+    pub ambiguous: Vec<WeightKey>,
+}
+
+impl WeightImport {
+    pub fn summary(&self) -> String {
+        let total = self.matched + self.unmatched.len() + self.ambiguous.len();
+        let mut summary = format!("Imported {} of {} weights", self.matched, total);
+        for (label, keys) in [
+            ("unmatched", &self.unmatched),
+            ("ambiguous", &self.ambiguous),
+        ] {
+            if !keys.is_empty() {
+                let keys = keys.iter().map(WeightKey::to_string).collect::<Vec<_>>();
+                summary.push_str(&format!(", {label}: {}", keys.join(", ")));
+            }
+        }
+        summary
+    }
+}
+
+pub fn import_weights(text: &str, tree: &TraitTree) -> Result<WeightImport, WeightImportError> {
+    let mut import = WeightImport::default();
+    for (key, values) in parse_weights(text)? {
+        match resolve_key(&key, tree).as_slice() {
+            [] => import.unmatched.push(key),
+            [target] => {
+                import.weights.set(*target, values);
+                import.matched += 1;
+            }
+            _ => import.ambiguous.push(key),
+        }
+    }
+    Ok(import)
+}
+
+#[component]
+pub fn WeightImportView(trait_tree: TraitTree, weights: RwSignal<Weights>) -> impl IntoView {
+    let trait_tree = StoredValue::new(trait_tree);
+    let import = Action::new_local(move |file: &File| {
+        let file = file.clone();
+        async move {
+            let text = JsFuture::from(file.text()).await?;
+            let imported = trait_tree
+                .with_value(|tree| import_weights(&text.as_string().unwrap_or_default(), tree))?;
+            weights.set(imported.weights.clone());
+            Ok::<_, WeightImportError>(imported)
+        }
+    });
+
+    view! {
+        <div>
+            <label>
+                "Import sim weights "
+                <input
+                    type="file"
+                    accept=".csv,.json,text/csv,application/json"
+                    on:change:target=move |tag| {
+                        if let Some(file) = tag.target().files().and_then(|files| files.get(0)) {
+                            import.dispatch_local(file);
+                        }
+                    }
+                />
+            </label>
+            {move || {
+                import
+                    .value()
+                    .get()
+                    .map(|result| match result {
+                        Ok(imported) => imported.summary(),
+                        Err(err) => err.to_string(),
+                    })
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_tree::test_tree;
+
+    fn name(name: &str) -> WeightKey {
+        WeightKey::Name(name.to_string())
+    }
+
+    fn target(node_id: usize, entry_id: Option<usize>) -> NodeTarget {
+        NodeTarget { node_id, entry_id }
+    }
+
+    #[test]
+    fn quoted_csv_fields() {
+        assert_eq!(
+            parse_weights("\"Steady, Aim\",1\n\"Say \"\"hi\"\"\", 2 ,3\n"),
+            Ok(vec![
+                (name("Steady, Aim"), vec![1.0]),
+                (name("Say \"hi\""), vec![2.0, 3.0]),
+            ])
+        );
+        assert_eq!(
+            parse_weights("\"Steady Aim,1").unwrap_err().to_string(),
+            "Line 1: unterminated quote"
+        );
+    }
+
+    #[test]
+    fn csv_headers_comments_and_errors() {
+        assert_eq!(
+            parse_weights("key,value\n# from a sim\n\nspell:1020,5\nentry:31,1.5"),
+            Ok(vec![
+                (WeightKey::Spell(1020), vec![5.0]),
+                (WeightKey::Entry(31), vec![1.5]),
+            ])
+        );
+        // only the first line may be a header
+        assert_eq!(
+            parse_weights("key,value\nQuick Shot,many")
+                .unwrap_err()
+                .to_string(),
+            "Line 2: many is not a number"
+        );
+        assert_eq!(
+            parse_weights("Quick Shot,1\nSteady Aim,\n")
+                .unwrap_err()
+                .to_string(),
+            "Line 2: no value for Steady Aim"
+        );
+        assert_eq!(parse_weights("key,value\n"), Err(WeightImportError::Empty));
+    }
+
+    #[test]
+    fn json_keys_and_records() {
+        assert_eq!(
+            parse_weights(r#"{ "Quick Shot": 1.5, "spell:1020": [3, 6] }"#),
+            Ok(vec![
+                (name("Quick Shot"), vec![1.5]),
+                (WeightKey::Spell(1020), vec![3.0, 6.0]),
+            ])
+        );
+        assert_eq!(
+            parse_weights(
+                r#"[
+                    { "name": "Quick Shot", "value": 1.5 },
+                    { "spell_id": 1020, "values": [3, 6] },
+                    { "entry_id": 31, "node_id": 3, "value": 2 },
+                    { "definition_id": 2031, "value": 4 }
+                ]"#
+            ),
+            Ok(vec![
+                (name("Quick Shot"), vec![1.5]),
+                (WeightKey::Spell(1020), vec![3.0, 6.0]),
+                (WeightKey::Node(3), vec![2.0]),
+                (WeightKey::Definition(2031), vec![4.0]),
+            ])
+        );
+        assert!(matches!(
+            parse_weights(r#"[{ "value": 1 }]"#),
+            Err(WeightImportError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_last_value() {
+        let tree = test_tree();
+        let import = import_weights("Quick Shot,1\nnode:2,5\n", &tree).unwrap();
+        assert_eq!(import.weights.get(&target(2, None)), Some(&[5.0][..]));
+        assert_eq!(
+            parse_weights(r#"{ "Quick Shot": 1, "Quick Shot": 4 }"#),
+            Ok(vec![(name("Quick Shot"), vec![4.0])])
+        );
+    }
+
+    #[test]
+    fn keys_resolve_by_name_and_ids() {
+        let tree = test_tree();
+        let resolve = |key: &str| resolve_key(&WeightKey::parse(key), &tree);
+
+        // names, node names first and case insensitive, then entry names
+        assert_eq!(resolve("quick shot"), vec![target(2, None)]);
+        assert_eq!(resolve("Right Path"), vec![target(3, Some(31))]);
+        // a bare number is a node id, then an entry id, then a spell id
+        assert_eq!(resolve("10"), vec![target(10, None)]);
+        assert_eq!(resolve("20"), vec![target(2, None)]);
+        assert_eq!(resolve("1031"), vec![target(3, Some(31))]);
+        // prefixes pick one kind of id
+        assert_eq!(resolve("entry:10"), vec![target(1, None)]);
+        assert_eq!(resolve("spell:1110"), vec![target(11, None)]);
+        assert_eq!(resolve("definition:2030"), vec![target(3, Some(30))]);
+        assert_eq!(resolve("node:20"), vec![]);
+    }
+
+    #[test]
+    fn unresolved_keys_are_reported() {
+        let mut tree = test_tree();
+        tree.spec_nodes[1].name = "Sharp Eye".to_string();
+        tree.build_index();
+
+        let import = import_weights(
+            "Nobody,1\nHero Choice,2\n2031,3\nSharp Eye,4\nSteady Aim,5",
+            &tree,
+        )
+        .unwrap();
+        assert_eq!(import.matched, 1);
+        assert_eq!(
+            import.unmatched,
+            vec![name("Nobody"), name("Hero Choice"), WeightKey::Id(2031)]
+        );
+        assert_eq!(import.ambiguous, vec![name("Sharp Eye")]);
+        assert_eq!(
+            import.summary(),
+            "Imported 1 of 5 weights, unmatched: \"Nobody\", \"Hero Choice\", 2031, \
+             ambiguous: \"Sharp Eye\""
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+const ID_LENGTH: usize = 6;
+const ID_CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const MAX_STRING_LENGTH: usize = 512;
+
+#[derive(Error, Debug)]
+pub enum BuildStoreError {
+    #[error("Talent string is empty")]
+    EmptyString,
+    #[error("Talent string is too long")]
+    StringTooLong,
+    #[error("Talent string contains characters not permitted for encoding configuration")]
+    InvalidCharacters,
+    #[error("Could not access build storage: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Build storage is corrupt: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredBuild {
+    pub string: String,
+    pub created: u64,
+    pub expires: u64,
+}
+
+#[derive(Default)]
+struct Builds {
+    by_id: HashMap<String, StoredBuild>,
+    // string to id, so a build already stored is found without a scan
+    ids: HashMap<String, String>,
+}
+
+impl Builds {
+    fn new(by_id: HashMap<String, StoredBuild>) -> Self {
+        let ids = by_id
+            .iter()
+            .map(|(id, build)| (build.string.clone(), id.clone()))
+            .collect();
+        Self { by_id, ids }
+    }
+
+    fn remove_expired(&mut self, now: u64) {
+        let ids = &mut self.ids;
+        self.by_id.retain(|_, build| {
+            let keep = build.expires > now;
+            if !keep {
+                ids.remove(&build.string);
+            }
+            keep
+        });
+    }
+}
+
+pub struct BuildStore {
+    path: PathBuf,
+    ttl: Duration,
+    // held across the write to disk, so requests persist one at a time
+    builds: Mutex<Builds>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// clients send canonical strings, whitespace is stripped so pasted ones still match
+pub fn canonicalize(string: &str) -> Result<String, BuildStoreError> {
+    let string = string
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+
+    match string.len() {
+        0 => Err(BuildStoreError::EmptyString),
+        len if len > MAX_STRING_LENGTH => Err(BuildStoreError::StringTooLong),
+        _ if !string
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/') =>
+        {
+            Err(BuildStoreError::InvalidCharacters)
+        }
+        _ => Ok(string),
+    }
+}
+
+// 64 bit FNV-1a of the string and attempt counter, mapped onto `ID_CHARS`
+fn short_id(string: &str, attempt: u64) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x00000100000001b3;
+
+    let mut hash = string
+        .bytes()
+        .chain(attempt.to_le_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        });
+
+    (0..ID_LENGTH)
+        .map(|_| {
+            let c = ID_CHARS[(hash % ID_CHARS.len() as u64) as usize] as char;
+            hash /= ID_CHARS.len() as u64;
+            c
+        })
+        .collect()
+}
+
+impl BuildStore {
+    pub async fn open(path: PathBuf, ttl: Duration) -> Result<Self, BuildStoreError> {
+        let builds = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path,
+            ttl,
+            builds: Mutex::new(Builds::new(builds)),
+        })
+    }
+
+    async fn persist(&self, builds: &Builds) -> Result<(), BuildStoreError> {
+        // write and sync next to the store, then rename, so a crash never leaves a truncated file
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&serde_json::to_vec(&builds.by_id)?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+
+    // storing a build that already exists returns its id and extends its expiration
+    pub async fn insert(&self, string: &str) -> Result<String, BuildStoreError> {
+        let string = canonicalize(string)?;
+        let now = now();
+        let expires = now + self.ttl.as_secs();
+
+        let mut builds = self.builds.lock().await;
+        builds.remove_expired(now);
+
+        let id = match builds.ids.get(&string) {
+            Some(id) => id.clone(),
+            None => (0..)
+                .map(|attempt| short_id(&string, attempt))
+                .find(|id| !builds.by_id.contains_key(id))
+                .expect("ran out of short ids"),
+        };
+
+        let created = builds
+            .by_id
+            .get(&id)
+            .map(|build| build.created)
+            .unwrap_or(now);
+        builds.ids.insert(string.clone(), id.clone());
+        builds.by_id.insert(
+            id.clone(),
+            StoredBuild {
+                string,
+                created,
+                expires,
+            },
+        );
+        self.persist(&builds).await?;
+
+        Ok(id)
+    }
+
+    pub async fn get(&self, id: &str) -> Option<StoredBuild> {
+        let now = now();
+        self.builds
+            .lock()
+            .await
+            .by_id
+            .get(id)
+            .filter(|build| build.expires > now)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    // a fresh store file per test, removed when the test finishes
+    struct TestPath(PathBuf);
+
+    impl TestPath {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("build-store-{name}-{}.json", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TestPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn canonicalize_strips_whitespace_and_rejects_bad_strings() {
+        assert_eq!(canonicalize(" CwQA\nAAA+/ ").unwrap(), "CwQAAAA+/");
+        assert!(matches!(
+            canonicalize(" \n"),
+            Err(BuildStoreError::EmptyString)
+        ));
+        assert!(matches!(
+            canonicalize(&"A".repeat(MAX_STRING_LENGTH + 1)),
+            Err(BuildStoreError::StringTooLong)
+        ));
+        assert!(matches!(
+            canonicalize("CwQA-AAA"),
+            Err(BuildStoreError::InvalidCharacters)
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_then_get() {
+        let path = TestPath::new("insert");
+        let store = BuildStore::open(path.0.clone(), DAY).await.unwrap();
+
+        let id = store.insert("CwQAAAAA").await.unwrap();
+        assert_eq!(id.len(), ID_LENGTH);
+        let build = store.get(&id).await.unwrap();
+        assert_eq!(build.string, "CwQAAAAA");
+        assert_eq!(build.expires, build.created + DAY.as_secs());
+        assert!(store.get("nothere").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn same_build_shares_an_id() {
+        let path = TestPath::new("dedup");
+        let store = BuildStore::open(path.0.clone(), DAY).await.unwrap();
+
+        let id = store.insert("CwQAAAAA").await.unwrap();
+        assert_eq!(store.insert(" CwQA AAAA\n").await.unwrap(), id);
+        assert_ne!(store.insert("CwQAAAAB").await.unwrap(), id);
+        assert_eq!(store.builds.lock().await.by_id.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn expired_builds_are_gone() {
+        let path = TestPath::new("expired");
+        let store = BuildStore::open(path.0.clone(), Duration::ZERO)
+            .await
+            .unwrap();
+
+        let id = store.insert("CwQAAAAA").await.unwrap();
+        assert!(store.get(&id).await.is_none());
+        store.insert("CwQAAAAB").await.unwrap();
+        let builds = store.builds.lock().await;
+        assert!(!builds.ids.contains_key("CwQAAAAA"));
+    }
+
+    #[tokio::test]
+    async fn builds_survive_a_reopen() {
+        let path = TestPath::new("persist");
+        let id = {
+            let store = BuildStore::open(path.0.clone(), DAY).await.unwrap();
+            store.insert("CwQAAAAA").await.unwrap()
+        };
+        assert!(!path.0.with_extension("tmp").exists());
+
+        let store = BuildStore::open(path.0.clone(), DAY).await.unwrap();
+        assert_eq!(store.get(&id).await.unwrap().string, "CwQAAAAA");
+        // the id map is rebuilt, so the same build is not stored twice
+        assert_eq!(store.insert("CwQAAAAA").await.unwrap(), id);
+        assert_eq!(store.builds.lock().await.by_id.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn corrupt_store_is_an_error() {
+        let path = TestPath::new("corrupt");
+        std::fs::write(&path.0, "not json").unwrap();
+        assert!(matches!(
+            BuildStore::open(path.0.clone(), DAY).await,
+            Err(BuildStoreError::Serialization(_))
+        ));
+    }
+}
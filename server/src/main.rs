@@ -1,19 +1,58 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use axum::response::Response as AxumResponse;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::Router;
 use axum::{
     body::Body,
-    extract::State,
+    extract::{FromRef, Path, State},
     http::{Request, Response, StatusCode, Uri},
-    response::IntoResponse,
+    response::{IntoResponse, Redirect},
+    Json,
 };
 use leptos::logging::log;
 use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
 use tower::ServiceExt;
 use tower_http::services::ServeDir;
 
+use crate::build_store::{BuildStore, StoredBuild};
+
+mod build_store;
+
+const DEFAULT_BUILD_STORE_PATH: &str = "builds.json";
+const DEFAULT_BUILD_TTL_DAYS: u64 = 90;
+
+#[derive(Clone)]
+pub struct AppState {
+    leptos_options: LeptosOptions,
+    builds: Arc<BuildStore>,
+}
+
+impl FromRef<AppState> for LeptosOptions {
+    fn from_ref(state: &AppState) -> Self {
+        state.leptos_options.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<BuildStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.builds.clone()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateBuild {
+    string: String,
+}
+
+#[derive(Serialize)]
+pub struct CreatedBuild {
+    id: String,
+}
+
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
         <!DOCTYPE html>
@@ -42,10 +81,29 @@ async fn main() {
         .await
         .expect("could not write index.html");
 
+    let build_store_path =
+        std::env::var("BUILD_STORE_PATH").unwrap_or_else(|_| DEFAULT_BUILD_STORE_PATH.to_string());
+    let build_ttl_days = std::env::var("BUILD_TTL_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_BUILD_TTL_DAYS);
+    let builds = BuildStore::open(
+        PathBuf::from(build_store_path),
+        Duration::from_secs(build_ttl_days * 24 * 60 * 60),
+    )
+    .await
+    .expect("could not open build store");
+
     let app = Router::new()
         .route("/", get(file_and_error_handler))
+        .route("/api/builds", post(create_build))
+        .route("/api/builds/{id}", get(get_build))
+        .route("/b/{id}", get(build_redirect))
         .fallback(file_and_error_handler)
-        .with_state(leptos_options);
+        .with_state(AppState {
+            leptos_options,
+            builds: Arc::new(builds),
+        });
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
@@ -56,6 +114,37 @@ async fn main() {
         .unwrap();
 }
 
+pub async fn create_build(
+    State(builds): State<Arc<BuildStore>>,
+    Json(request): Json<CreateBuild>,
+) -> Result<Json<CreatedBuild>, (StatusCode, String)> {
+    match builds.insert(&request.string).await {
+        Ok(id) => Ok(Json(CreatedBuild { id })),
+        Err(err @ build_store::BuildStoreError::Io(_))
+        | Err(err @ build_store::BuildStoreError::Serialization(_)) => {
+            Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+        }
+        Err(err) => Err((StatusCode::BAD_REQUEST, err.to_string())),
+    }
+}
+
+pub async fn get_build(
+    Path(id): Path<String>,
+    State(builds): State<Arc<BuildStore>>,
+) -> Result<Json<StoredBuild>, StatusCode> {
+    builds.get(&id).await.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn build_redirect(
+    Path(id): Path<String>,
+    State(builds): State<Arc<BuildStore>>,
+) -> Result<Redirect, StatusCode> {
+    // talent strings are base64, only `+` and `/` need escaping in a query
+    let build = builds.get(&id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let talents = build.string.replace('+', "%2B").replace('/', "%2F");
+    Ok(Redirect::to(&format!("/?talents={talents}")))
+}
+
 pub async fn file_and_error_handler(
     uri: Uri,
     State(options): State<LeptosOptions>,